@@ -1,29 +1,36 @@
 use glam::Mat4;
 use glam::Vec3;
 
+use crate::engine::transform::Transform;
+
+// Looks along the -z axis of its transform. Scale is ignored.
 pub struct Camera {
-    pub proj: Mat4,
-    pub view: Mat4,
+    pub transform: Transform,
+    // Vertical field of view in radians.
+    pub fov_y: f32,
+    aspect_ratio: f32,
 }
 
 impl Camera {
     pub fn new(aspect_ratio: f32) -> Self {
         Camera {
-            proj: get_projection_matrix(aspect_ratio),
-            view: Mat4::look_at_rh(
-                Vec3::new(0.0, 0.0, 1.0),
-                Vec3::new(0.0, 0.0, 0.0),
-                Vec3::new(0.0, 1.0, 0.0),
-            ),
+            transform: Transform::from_translation(Vec3::new(0.0, 0.0, 1.0)),
+            fov_y: std::f32::consts::FRAC_PI_2,
+            aspect_ratio,
         }
     }
 
+    pub fn view(&self) -> Mat4 {
+        Mat4::from_rotation_translation(self.transform.rotation, self.transform.translation)
+            .inverse()
+    }
+
+    pub fn proj(&self) -> Mat4 {
+        Mat4::perspective_rh_gl(self.fov_y, self.aspect_ratio, 0.01, 100.0)
+    }
+
     // Must be called if aspect ratio of window was changed.
     pub fn update_projection(&mut self, aspect_ratio: f32) {
-        self.proj = get_projection_matrix(aspect_ratio);
+        self.aspect_ratio = aspect_ratio;
     }
 }
-
-fn get_projection_matrix(aspect_ratio: f32) -> Mat4 {
-    Mat4::perspective_rh_gl(std::f32::consts::FRAC_PI_2, aspect_ratio, 0.01, 100.0)
-}
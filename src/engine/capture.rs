@@ -0,0 +1,92 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use vulkano::format::Format;
+
+use crate::engine::error::MagmaError;
+
+// A rendered frame read back from the GPU, stored as tightly packed RGBA8.
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+// Color attachment formats that `Frame::from_raw` can convert.
+pub fn supports_format(format: Format) -> bool {
+    matches!(
+        format,
+        Format::R8G8B8A8_UNORM
+            | Format::R8G8B8A8_SRGB
+            | Format::B8G8R8A8_UNORM
+            | Format::B8G8R8A8_SRGB
+    )
+}
+
+impl Frame {
+    // Converts the raw contents of a color attachment into RGBA8, see
+    // `supports_format`.
+    pub fn from_raw(
+        width: u32,
+        height: u32,
+        format: Format,
+        mut data: Vec<u8>,
+    ) -> Result<Self, MagmaError> {
+        match format {
+            Format::R8G8B8A8_UNORM | Format::R8G8B8A8_SRGB => {}
+            Format::B8G8R8A8_UNORM | Format::B8G8R8A8_SRGB => {
+                for pixel in data.chunks_exact_mut(4) {
+                    pixel.swap(0, 2);
+                }
+            }
+            _ => {
+                return Err(MagmaError::CaptureUnsupported(format!("format {format:?}")));
+            }
+        }
+        Ok(Frame {
+            width,
+            height,
+            pixels: data,
+        })
+    }
+
+    pub fn encode_png(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut data, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder
+            .write_header()
+            .expect("engine: failed to write png header");
+        writer
+            .write_image_data(&self.pixels)
+            .expect("engine: failed to write png data");
+        writer.finish().expect("engine: failed to finish png");
+        data
+    }
+
+    // Binary PPM (P6) without alpha channel.
+    pub fn encode_ppm(&self) -> Vec<u8> {
+        let mut data = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        for pixel in self.pixels.chunks_exact(4) {
+            data.extend_from_slice(&pixel[..3]);
+        }
+        data
+    }
+
+    // Writes the frame as PNG or PPM, depending on the file extension.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let data = match path.extension().and_then(|e| e.to_str()) {
+            Some("png") => self.encode_png(),
+            Some("ppm") => self.encode_ppm(),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unsupported image extension: {}", path.display()),
+                ));
+            }
+        };
+        fs::write(path, data)
+    }
+}
@@ -0,0 +1,143 @@
+use std::sync::Arc;
+use vulkano::VulkanLibrary;
+use vulkano::instance::debug::DebugUtilsMessageSeverity;
+use vulkano::instance::debug::DebugUtilsMessageType;
+use vulkano::instance::debug::DebugUtilsMessenger;
+use vulkano::instance::debug::DebugUtilsMessengerCallback;
+use vulkano::instance::debug::DebugUtilsMessengerCreateInfo;
+use vulkano::instance::{Instance, InstanceExtensions};
+
+use crate::engine::error::MagmaError;
+
+pub const VALIDATION_LAYER: &str = "VK_LAYER_KHRONOS_validation";
+
+// Lowest severity of the Vulkan debug messages that are printed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DebugSeverity {
+    Verbose,
+    Info,
+    Warning,
+    Error,
+}
+
+impl DebugSeverity {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "verbose" => Some(DebugSeverity::Verbose),
+            "info" => Some(DebugSeverity::Info),
+            "warning" => Some(DebugSeverity::Warning),
+            "error" => Some(DebugSeverity::Error),
+            _ => None,
+        }
+    }
+
+    // This severity and all higher ones.
+    fn flags(self) -> DebugUtilsMessageSeverity {
+        let all = [
+            (DebugSeverity::Verbose, DebugUtilsMessageSeverity::VERBOSE),
+            (DebugSeverity::Info, DebugUtilsMessageSeverity::INFO),
+            (DebugSeverity::Warning, DebugUtilsMessageSeverity::WARNING),
+            (DebugSeverity::Error, DebugUtilsMessageSeverity::ERROR),
+        ];
+        all.into_iter()
+            .filter(|(severity, _)| *severity >= self)
+            .fold(DebugUtilsMessageSeverity::empty(), |flags, (_, flag)| {
+                flags | flag
+            })
+    }
+}
+
+// Like `create_instance`, but with the validation layer enabled and debug
+// messages of at least `min_severity` printed for as long as the returned
+// messenger is alive. Without the layer or the debug utils extension, e.g.
+// when the Vulkan SDK is not installed, the instance is created anyway and
+// only a notice is printed.
+pub fn create_debug_instance(
+    enabled_extensions: InstanceExtensions,
+    min_severity: DebugSeverity,
+) -> Result<(Arc<Instance>, Option<DebugUtilsMessenger>), MagmaError> {
+    let library = VulkanLibrary::new()?;
+    let mut enabled_layers = Vec::new();
+    if library
+        .layer_properties()?
+        .any(|layer| layer.name() == VALIDATION_LAYER)
+    {
+        enabled_layers.push(VALIDATION_LAYER.to_string());
+    } else {
+        println!("engine: {VALIDATION_LAYER} is not available");
+    }
+    let debug_utils = library
+        .supported_extensions_with_layers(enabled_layers.iter().map(String::as_str))?
+        .ext_debug_utils;
+    let instance = crate::engine::new_instance(
+        library,
+        InstanceExtensions {
+            ext_debug_utils: debug_utils,
+            ..enabled_extensions
+        },
+        enabled_layers,
+    )?;
+    if !debug_utils {
+        println!("engine: debug messages are not available");
+        return Ok((instance, None));
+    }
+    // The callback must not call into Vulkan, which printing doesn't.
+    let callback = unsafe {
+        DebugUtilsMessengerCallback::new(|severity, message_type, data| {
+            println!(
+                "engine: vulkan {} {}: {}",
+                severity_name(severity),
+                type_name(message_type),
+                data.message
+            );
+        })
+    };
+    let messenger = DebugUtilsMessenger::new(
+        instance.clone(),
+        DebugUtilsMessengerCreateInfo {
+            message_severity: min_severity.flags(),
+            message_type: DebugUtilsMessageType::GENERAL
+                | DebugUtilsMessageType::VALIDATION
+                | DebugUtilsMessageType::PERFORMANCE,
+            ..DebugUtilsMessengerCreateInfo::user_callback(callback)
+        },
+    )?;
+    Ok((instance, Some(messenger)))
+}
+
+fn severity_name(severity: DebugUtilsMessageSeverity) -> &'static str {
+    if severity.intersects(DebugUtilsMessageSeverity::ERROR) {
+        "error"
+    } else if severity.intersects(DebugUtilsMessageSeverity::WARNING) {
+        "warning"
+    } else if severity.intersects(DebugUtilsMessageSeverity::INFO) {
+        "info"
+    } else {
+        "verbose"
+    }
+}
+
+fn type_name(message_type: DebugUtilsMessageType) -> &'static str {
+    if message_type.intersects(DebugUtilsMessageType::VALIDATION) {
+        "validation"
+    } else if message_type.intersects(DebugUtilsMessageType::PERFORMANCE) {
+        "performance"
+    } else {
+        "general"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn severity_flags() {
+        assert_eq!(
+            DebugSeverity::Warning.flags(),
+            DebugUtilsMessageSeverity::WARNING | DebugUtilsMessageSeverity::ERROR
+        );
+        assert_eq!(DebugSeverity::parse("Info"), Some(DebugSeverity::Info));
+        assert_eq!(DebugSeverity::parse("loud"), None);
+    }
+}
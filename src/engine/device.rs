@@ -1,5 +1,7 @@
+use std::fmt;
 use std::sync::Arc;
 
+use vulkano::Version;
 use vulkano::device::DeviceExtensions;
 use vulkano::device::Queue;
 use vulkano::device::QueueFlags;
@@ -9,18 +11,164 @@ use vulkano::device::{Device, DeviceCreateInfo, QueueCreateInfo};
 use vulkano::instance::Instance;
 use vulkano::swapchain::Surface;
 
+use crate::engine::error::MagmaError;
+
+// Environment variable that pins the device, in the format of `parse`.
+pub const DEVICE_ENV_VAR: &str = "MAGMA_DEVICE";
+
+// Which physical device the engine renders with. Devices that lack a graphics
+// queue, presentation support or a required extension are never selected.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum DeviceSelection {
+    // The best suitable device, preferring discrete over integrated GPUs.
+    #[default]
+    Auto,
+    // The best suitable device whose name contains the string, ignoring case.
+    Name(String),
+    // The device with the PCI vendor and device ID.
+    Id {
+        vendor_id: u32,
+        device_id: u32,
+    },
+    // The device at this position in the list of `list_devices`.
+    Index(usize),
+}
+
+impl DeviceSelection {
+    // `#<index>` selects by index, e.g. `#1`, `<vendor>:<device>` with
+    // hexadecimal IDs by ID, e.g. `10de:2684`, "auto" or an empty string
+    // automatically, and anything else by name, including plain numbers like
+    // `4090`.
+    pub fn parse(value: &str) -> Self {
+        let value = value.trim();
+        if value.is_empty() || value.eq_ignore_ascii_case("auto") {
+            return DeviceSelection::Auto;
+        }
+        if let Some(index) = value.strip_prefix('#')
+            && let Ok(index) = index.parse()
+        {
+            return DeviceSelection::Index(index);
+        }
+        if let Some((vendor_id, device_id)) = value.split_once(':')
+            && let Ok(vendor_id) = u32::from_str_radix(vendor_id, 16)
+            && let Ok(device_id) = u32::from_str_radix(device_id, 16)
+        {
+            return DeviceSelection::Id {
+                vendor_id,
+                device_id,
+            };
+        }
+        DeviceSelection::Name(value.to_string())
+    }
+
+    // The selection in `MAGMA_DEVICE`, or `Auto` if it is not set.
+    pub fn from_env() -> Self {
+        std::env::var(DEVICE_ENV_VAR).map_or(DeviceSelection::Auto, |value| Self::parse(&value))
+    }
+
+    fn matches(&self, index: usize, physical_device: &PhysicalDevice) -> bool {
+        let properties = physical_device.properties();
+        match self {
+            DeviceSelection::Auto => true,
+            DeviceSelection::Name(name) => properties
+                .device_name
+                .to_lowercase()
+                .contains(&name.to_lowercase()),
+            DeviceSelection::Id {
+                vendor_id,
+                device_id,
+            } => properties.vendor_id == *vendor_id && properties.device_id == *device_id,
+            DeviceSelection::Index(i) => index == *i,
+        }
+    }
+}
+
+// In the format of `parse`.
+impl fmt::Display for DeviceSelection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeviceSelection::Auto => write!(f, "auto"),
+            DeviceSelection::Name(name) => write!(f, "{name}"),
+            DeviceSelection::Id {
+                vendor_id,
+                device_id,
+            } => write!(f, "{vendor_id:04x}:{device_id:04x}"),
+            DeviceSelection::Index(index) => write!(f, "#{index}"),
+        }
+    }
+}
+
+// Properties of a physical device as reported by `list_devices`.
+#[derive(Clone, Debug)]
+pub struct DeviceInfo {
+    pub index: usize,
+    pub name: String,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub device_type: PhysicalDeviceType,
+    pub api_version: Version,
+    // Why the engine can't use the device, `None` if it is suitable.
+    pub rejection: Option<String>,
+}
+
+impl fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "#{}: {} ({:04x}:{:04x}, {:?}, Vulkan {})",
+            self.index,
+            self.name,
+            self.vendor_id,
+            self.device_id,
+            self.device_type,
+            self.api_version
+        )?;
+        match &self.rejection {
+            Some(reason) => write!(f, ", rejected: {reason}"),
+            None => Ok(()),
+        }
+    }
+}
+
+// All physical devices of the instance, in the order used by
+// `DeviceSelection::Index`. With a surface, devices that can't present to it
+// are rejected, without one only rendering is required.
+pub fn list_devices(
+    instance: &Arc<Instance>,
+    surface: Option<&Arc<Surface>>,
+) -> Result<Vec<DeviceInfo>, MagmaError> {
+    let device_extensions = required_extensions(surface);
+    let devices = instance
+        .enumerate_physical_devices()?
+        .enumerate()
+        .map(|(index, p)| {
+            let properties = p.properties();
+            DeviceInfo {
+                index,
+                name: properties.device_name.clone(),
+                vendor_id: properties.vendor_id,
+                device_id: properties.device_id,
+                device_type: properties.device_type,
+                api_version: p.api_version(),
+                rejection: queue_family(&p, surface, &device_extensions).err(),
+            }
+        })
+        .collect();
+    Ok(devices)
+}
+
+// Without a surface the device is used for offscreen rendering only, so
+// neither presentation support nor the swapchain extension is required.
 pub fn init_device(
     instance: &Arc<Instance>,
-    surface: &Arc<Surface>,
-) -> (Arc<PhysicalDevice>, Arc<Device>, Arc<Queue>) {
-    let device_extensions = DeviceExtensions {
-        khr_swapchain: true,
-        ..DeviceExtensions::empty()
-    };
+    surface: Option<&Arc<Surface>>,
+    selection: &DeviceSelection,
+) -> Result<(Arc<Device>, Arc<Queue>), MagmaError> {
+    let device_extensions = required_extensions(surface);
     let (physical_device, queue_family_index) =
-        select_physical_device(&instance, &surface, &device_extensions);
+        select_physical_device(instance, surface, &device_extensions, selection)?;
     let (device, mut queues) = Device::new(
-        physical_device.clone(),
+        physical_device,
         DeviceCreateInfo {
             queue_create_infos: vec![QueueCreateInfo {
                 queue_family_index,
@@ -29,34 +177,37 @@ pub fn init_device(
             enabled_extensions: device_extensions,
             ..Default::default()
         },
-    )
-    .expect("engine: failed to create device");
-    let queue = queues.next().unwrap();
-    (physical_device, device, queue)
+    )?;
+    // One queue was requested, so exactly one is returned.
+    let queue = queues.next().expect("engine: device has no queue");
+    Ok((device, queue))
+}
+
+fn required_extensions(surface: Option<&Arc<Surface>>) -> DeviceExtensions {
+    DeviceExtensions {
+        khr_swapchain: surface.is_some(),
+        ..DeviceExtensions::empty()
+    }
 }
 
 pub fn select_physical_device(
     instance: &Arc<Instance>,
-    surface: &Arc<Surface>,
+    surface: Option<&Arc<Surface>>,
     device_extensions: &DeviceExtensions,
-) -> (Arc<PhysicalDevice>, u32) {
-    instance
-        .enumerate_physical_devices()
-        .expect("engine: could not enumerate devices")
-        .filter(|p| p.supported_extensions().contains(&device_extensions))
-        .filter_map(|p| {
-            p.queue_family_properties()
-                .iter()
-                .enumerate()
-                // Find the first first queue family that is suitable.
-                // If none is found, `None` is returned to `filter_map`,
-                // which disqualifies this physical device.
-                .position(|(i, q)| {
-                    q.queue_flags.contains(QueueFlags::GRAPHICS)
-                        && p.surface_support(i as u32, &surface).unwrap_or(false)
-                })
-                .map(|q| (p, q as u32))
+    selection: &DeviceSelection,
+) -> Result<(Arc<PhysicalDevice>, u32), MagmaError> {
+    let candidates = instance
+        .enumerate_physical_devices()?
+        .enumerate()
+        .filter(|(index, p)| selection.matches(*index, p))
+        .map(|(_, p)| {
+            let queue_family_index = queue_family(&p, surface, device_extensions);
+            (p, queue_family_index)
         })
+        .collect::<Vec<_>>();
+    let best = candidates
+        .iter()
+        .filter_map(|(p, queue_family_index)| Some((p.clone(), *queue_family_index.as_ref().ok()?)))
         .min_by_key(|(p, _)| match p.properties().device_type {
             PhysicalDeviceType::DiscreteGpu => 0,
             PhysicalDeviceType::IntegratedGpu => 1,
@@ -66,6 +217,85 @@ pub fn select_physical_device(
             // `PhysicalDeviceType` is a non-exhaustive enum. Thus, one should
             // match wildcard `_` to catch all unknown device types.
             _ => 4,
+        });
+    if let Some(best) = best {
+        return Ok(best);
+    }
+    // A pinned selection explains why it can't be used.
+    match selection {
+        DeviceSelection::Auto => Err(MagmaError::NoSuitableDevice),
+        _ if candidates.is_empty() => Err(MagmaError::DeviceNotSelectable(format!(
+            "no device matches {selection}"
+        ))),
+        _ => Err(MagmaError::DeviceNotSelectable(
+            candidates
+                .iter()
+                .filter_map(|(p, queue_family_index)| {
+                    let reason = queue_family_index.as_ref().err()?;
+                    Some(format!(
+                        "{} is rejected: {reason}",
+                        p.properties().device_name
+                    ))
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+        )),
+    }
+}
+
+// The first queue family that supports graphics and, with a surface,
+// presentation, or the reason why the device is unsuitable.
+fn queue_family(
+    physical_device: &PhysicalDevice,
+    surface: Option<&Arc<Surface>>,
+    device_extensions: &DeviceExtensions,
+) -> Result<u32, String> {
+    let missing_extensions = device_extensions.difference(physical_device.supported_extensions());
+    if !missing_extensions.is_empty() {
+        return Err(format!("missing extensions {missing_extensions:?}"));
+    }
+    let families = physical_device.queue_family_properties();
+    let graphics_families = (0..families.len() as u32)
+        .filter(|&i| {
+            families[i as usize]
+                .queue_flags
+                .contains(QueueFlags::GRAPHICS)
         })
-        .expect("engine: no device available")
+        .collect::<Vec<_>>();
+    if graphics_families.is_empty() {
+        return Err("no graphics queue".to_string());
+    }
+    graphics_families
+        .into_iter()
+        .find(|&i| {
+            surface
+                .is_none_or(|surface| physical_device.surface_support(i, surface).unwrap_or(false))
+        })
+        .ok_or_else(|| "no graphics queue can present to the surface".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_selection() {
+        assert_eq!(DeviceSelection::parse(""), DeviceSelection::Auto);
+        assert_eq!(DeviceSelection::parse("#1"), DeviceSelection::Index(1));
+        assert_eq!(
+            DeviceSelection::parse("4090"),
+            DeviceSelection::Name("4090".to_string())
+        );
+        assert_eq!(
+            DeviceSelection::parse("10de:2684"),
+            DeviceSelection::Id {
+                vendor_id: 0x10de,
+                device_id: 0x2684,
+            }
+        );
+        assert_eq!(
+            DeviceSelection::parse(" GeForce "),
+            DeviceSelection::Name("GeForce".to_string())
+        );
+    }
 }
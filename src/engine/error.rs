@@ -0,0 +1,156 @@
+use std::error::Error;
+use std::fmt;
+use vulkano::buffer::AllocateBufferError;
+use vulkano::command_buffer::CommandBufferExecError;
+use vulkano::image::AllocateImageError;
+use vulkano::library::LoadingError;
+use vulkano::memory::allocator::MemoryAllocatorError;
+use vulkano::pipeline::layout::IntoPipelineLayoutCreateInfoError;
+use vulkano::swapchain::FromWindowError;
+use vulkano::sync::HostAccessError;
+use vulkano::{Validated, ValidationError, VulkanError};
+
+#[derive(Debug)]
+pub enum MagmaError {
+    NoVulkanLibrary(LoadingError),
+    // No device supports graphics and, with a window, presentation to it.
+    NoSuitableDevice,
+    // The device pinned by a `DeviceSelection` doesn't exist or is
+    // unsuitable, with the reason.
+    DeviceNotSelectable(String),
+    // The window surface is gone, e.g. because the window was closed.
+    SurfaceLost,
+    OutOfDeviceMemory,
+    OutOfHostMemory,
+    // A shader module could not be created or lacks its entry point.
+    ShaderLoad(String),
+    // Frames of the render target can't be read back, with the reason.
+    CaptureUnsupported(String),
+    // An offscreen rendering function was called on a windowed engine.
+    NotHeadless,
+    // Zero frames in flight were requested.
+    NoFramesInFlight,
+    Vulkan(VulkanError),
+    // Invalid use of the Vulkan API, i.e. a bug in the engine.
+    Validation(Box<ValidationError>),
+    // Any other failure reported by vulkano.
+    Other(Box<dyn Error + Send + Sync>),
+}
+
+impl fmt::Display for MagmaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MagmaError::NoVulkanLibrary(e) => write!(f, "engine: no Vulkan library: {e}"),
+            MagmaError::NoSuitableDevice => write!(f, "engine: no suitable device available"),
+            MagmaError::DeviceNotSelectable(reason) => {
+                write!(f, "engine: selected device is not available: {reason}")
+            }
+            MagmaError::SurfaceLost => write!(f, "engine: surface lost"),
+            MagmaError::OutOfDeviceMemory => write!(f, "engine: out of device memory"),
+            MagmaError::OutOfHostMemory => write!(f, "engine: out of host memory"),
+            MagmaError::ShaderLoad(e) => write!(f, "engine: failed to load shader: {e}"),
+            MagmaError::CaptureUnsupported(reason) => {
+                write!(f, "engine: capturing frames is not supported: {reason}")
+            }
+            MagmaError::NotHeadless => write!(f, "engine: only available in headless mode"),
+            MagmaError::NoFramesInFlight => {
+                write!(f, "engine: at least one frame must be in flight")
+            }
+            MagmaError::Vulkan(e) => write!(f, "engine: {e}"),
+            MagmaError::Validation(e) => write!(f, "engine: {e}"),
+            MagmaError::Other(e) => write!(f, "engine: {e}"),
+        }
+    }
+}
+
+impl Error for MagmaError {}
+
+impl From<VulkanError> for MagmaError {
+    fn from(e: VulkanError) -> Self {
+        match e {
+            VulkanError::SurfaceLost => MagmaError::SurfaceLost,
+            VulkanError::OutOfDeviceMemory => MagmaError::OutOfDeviceMemory,
+            VulkanError::OutOfHostMemory => MagmaError::OutOfHostMemory,
+            e => MagmaError::Vulkan(e),
+        }
+    }
+}
+
+impl From<Box<ValidationError>> for MagmaError {
+    fn from(e: Box<ValidationError>) -> Self {
+        MagmaError::Validation(e)
+    }
+}
+
+impl<E: Into<MagmaError>> From<Validated<E>> for MagmaError {
+    fn from(e: Validated<E>) -> Self {
+        match e {
+            Validated::Error(e) => e.into(),
+            Validated::ValidationError(e) => MagmaError::Validation(e),
+        }
+    }
+}
+
+impl From<LoadingError> for MagmaError {
+    fn from(e: LoadingError) -> Self {
+        MagmaError::NoVulkanLibrary(e)
+    }
+}
+
+impl From<MemoryAllocatorError> for MagmaError {
+    fn from(e: MemoryAllocatorError) -> Self {
+        match e {
+            MemoryAllocatorError::AllocateDeviceMemory(e) => e.into(),
+            MemoryAllocatorError::OutOfPoolMemory => MagmaError::OutOfDeviceMemory,
+            e => MagmaError::Other(Box::new(e)),
+        }
+    }
+}
+
+impl From<AllocateBufferError> for MagmaError {
+    fn from(e: AllocateBufferError) -> Self {
+        match e {
+            AllocateBufferError::CreateBuffer(e) | AllocateBufferError::BindMemory(e) => e.into(),
+            AllocateBufferError::AllocateMemory(e) => e.into(),
+        }
+    }
+}
+
+impl From<AllocateImageError> for MagmaError {
+    fn from(e: AllocateImageError) -> Self {
+        match e {
+            AllocateImageError::CreateImage(e) | AllocateImageError::BindMemory(e) => e.into(),
+            AllocateImageError::AllocateMemory(e) => e.into(),
+        }
+    }
+}
+
+impl From<FromWindowError> for MagmaError {
+    fn from(e: FromWindowError) -> Self {
+        match e {
+            FromWindowError::CreateSurface(e) => e.into(),
+            e => MagmaError::Other(Box::new(e)),
+        }
+    }
+}
+
+impl From<HostAccessError> for MagmaError {
+    fn from(e: HostAccessError) -> Self {
+        match e {
+            HostAccessError::Invalidate(e) => e.into(),
+            e => MagmaError::Other(Box::new(e)),
+        }
+    }
+}
+
+impl From<CommandBufferExecError> for MagmaError {
+    fn from(e: CommandBufferExecError) -> Self {
+        MagmaError::Other(Box::new(e))
+    }
+}
+
+impl From<IntoPipelineLayoutCreateInfoError> for MagmaError {
+    fn from(e: IntoPipelineLayoutCreateInfoError) -> Self {
+        e.error.into()
+    }
+}
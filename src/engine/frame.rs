@@ -0,0 +1,66 @@
+use std::sync::Arc;
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::allocator::{SubbufferAllocator, SubbufferAllocatorCreateInfo};
+use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
+use vulkano::device::Device;
+use vulkano::memory::allocator::{MemoryTypeFilter, StandardMemoryAllocator};
+use vulkano::sync::GpuFuture;
+use vulkano::sync::future::FenceSignalFuture;
+
+use crate::engine::error::MagmaError;
+
+pub const DEFAULT_FRAMES_IN_FLIGHT: usize = 2;
+
+pub type FrameFence = Arc<FenceSignalFuture<Box<dyn GpuFuture + Send + Sync>>>;
+
+// Resources for recording and executing one frame. The CPU records the next
+// frame with another set while the GPU still executes this one, and only
+// reuses this set after its fence has signaled.
+pub struct FrameResources {
+    pub command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+    pub uniform_buffer_allocator: SubbufferAllocator,
+    pub instance_buffer_allocator: SubbufferAllocator,
+    // Signaled when the last submission with these resources has finished.
+    pub fence: Option<FrameFence>,
+}
+
+impl FrameResources {
+    pub fn new(device: &Arc<Device>, memory_allocator: &Arc<StandardMemoryAllocator>) -> Self {
+        let command_buffer_allocator = Arc::new(StandardCommandBufferAllocator::new(
+            device.clone(),
+            Default::default(),
+        ));
+        let uniform_buffer_allocator = SubbufferAllocator::new(
+            memory_allocator.clone(),
+            SubbufferAllocatorCreateInfo {
+                buffer_usage: BufferUsage::UNIFORM_BUFFER,
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+        );
+        let instance_buffer_allocator = SubbufferAllocator::new(
+            memory_allocator.clone(),
+            SubbufferAllocatorCreateInfo {
+                buffer_usage: BufferUsage::VERTEX_BUFFER,
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+        );
+        FrameResources {
+            command_buffer_allocator,
+            uniform_buffer_allocator,
+            instance_buffer_allocator,
+            fence: None,
+        }
+    }
+
+    // Blocks until the GPU no longer uses these resources.
+    pub fn wait(&mut self) -> Result<(), MagmaError> {
+        if let Some(fence) = self.fence.take() {
+            fence.wait(None)?;
+        }
+        Ok(())
+    }
+}
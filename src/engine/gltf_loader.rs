@@ -0,0 +1,248 @@
+use glam::Mat4;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::engine::material::Material;
+use crate::engine::mesh::{self, Mesh, MeshSource, Normal, Position, Uv};
+use crate::engine::model::Model;
+use crate::engine::scene::{ModelHandle, Scene};
+use crate::engine::transform::Transform;
+
+// Imports the default scene (or the first one) of a `.gltf` or `.glb` file.
+// Every primitive of a glTF mesh becomes a `Mesh` that is shared by all
+// nodes referencing it. Models are named after their nodes. Base color and
+// emissive factors of the glTF materials become model materials.
+pub fn load(path: impl AsRef<Path>) -> Result<Scene, gltf::Error> {
+    let path = path.as_ref();
+    let (document, buffers, _images) = gltf::import(path)?;
+    Ok(load_document(&document, &buffers, Some(path)))
+}
+
+// All meshes of the file, with one `Mesh` per triangle primitive.
+pub fn load_meshes(path: impl AsRef<Path>) -> Result<Vec<Vec<Arc<Mesh>>>, gltf::Error> {
+    let path = path.as_ref();
+    let (document, buffers, _images) = gltf::import(path)?;
+    Ok(document
+        .meshes()
+        .map(|mesh| {
+            load_mesh(&mesh, &buffers, Some(path))
+                .into_iter()
+                .map(|(mesh, _)| mesh)
+                .collect()
+        })
+        .collect())
+}
+
+// `path` is the file the document was read from, which is recorded as
+// source of the meshes.
+fn load_document(
+    document: &gltf::Document,
+    buffers: &[gltf::buffer::Data],
+    path: Option<&Path>,
+) -> Scene {
+    let meshes = document
+        .meshes()
+        .map(|mesh| load_mesh(&mesh, buffers, path))
+        .collect::<Vec<_>>();
+    let mut scene = Scene::new();
+    if let Some(gltf_scene) = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        for node in gltf_scene.nodes() {
+            add_node(&mut scene, &meshes, &node, None, Mat4::IDENTITY);
+        }
+    }
+    scene
+}
+
+fn load_mesh(
+    mesh: &gltf::Mesh,
+    buffers: &[gltf::buffer::Data],
+    path: Option<&Path>,
+) -> Vec<(Arc<Mesh>, Material)> {
+    mesh.primitives()
+        .filter(|primitive| primitive.mode() == gltf::mesh::Mode::Triangles)
+        .filter_map(|primitive| {
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let positions = reader
+                .read_positions()?
+                .map(|position| Position { position })
+                .collect::<Vec<_>>();
+            let indices = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..positions.len() as u32).collect::<Vec<_>>(),
+            };
+            let normals = match reader.read_normals() {
+                Some(normals) => normals.map(|normal| Normal { normal }).collect(),
+                None => mesh::compute_normals(&positions, &indices),
+            };
+            let mut result = Mesh::new(positions, normals, indices);
+            let uvs = reader
+                .read_tex_coords(0)
+                .map(|uvs| uvs.into_f32().map(|uv| Uv { uv }).collect::<Vec<_>>());
+            if let Some(uvs) = uvs.filter(|uvs| uvs.len() == result.positions().len()) {
+                result = result.with_uvs(uvs);
+            }
+            if let Some(path) = path {
+                result = result.with_source(MeshSource::Gltf {
+                    path: path.to_path_buf(),
+                    mesh: mesh.index(),
+                    primitive: primitive.index(),
+                });
+            }
+            Some((Arc::new(result), load_material(&primitive.material())))
+        })
+        .collect()
+}
+
+// Primitives without material keep the default material instead of the
+// white default material of glTF.
+fn load_material(material: &gltf::Material) -> Material {
+    if material.index().is_none() {
+        return Material::DEFAULT;
+    }
+    let [r, g, b, _] = material.pbr_metallic_roughness().base_color_factor();
+    Material {
+        base_color: [r, g, b],
+        emissive: material.emissive_factor(),
+        ..Material::DEFAULT
+    }
+}
+
+// Nodes with a mesh become models and keep their place in the hierarchy.
+// Transforms of nodes without mesh, or whose mesh has no triangle
+// primitives, are accumulated into `transform` and applied to their
+// descendant models.
+fn add_node(
+    scene: &mut Scene,
+    meshes: &[Vec<(Arc<Mesh>, Material)>],
+    node: &gltf::Node,
+    parent: Option<ModelHandle>,
+    transform: Mat4,
+) {
+    let transform = transform * Mat4::from_cols_array_2d(&node.transform().matrix());
+    let primitives = node.mesh().map_or(&[][..], |mesh| &meshes[mesh.index()]);
+    // Further primitives of the mesh become children of the first one.
+    let Some((first, rest)) = primitives.split_first() else {
+        for child in node.children() {
+            add_node(scene, meshes, &child, parent, transform);
+        }
+        return;
+    };
+    let name = node
+        .name()
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("node{}", node.index()));
+    let mut model = Model::new(first.0.clone());
+    model.material = first.1;
+    model.transform = Transform::from_matrix(transform);
+    // glTF does not require unique node names, the scene makes them unique.
+    let handle = scene.insert(name.clone(), model);
+    // New models have no children, so attaching them can't form a cycle.
+    scene
+        .set_parent(handle, parent)
+        .expect("gltf: parent is in the scene");
+    for (i, (mesh, material)) in rest.iter().enumerate() {
+        let mut model = Model::new(mesh.clone());
+        model.material = *material;
+        let child = scene.insert(format!("{name}.{}", i + 1), model);
+        scene
+            .set_parent(child, Some(handle))
+            .expect("gltf: parent is in the scene");
+    }
+    for child in node.children() {
+        add_node(scene, meshes, &child, Some(handle), Mat4::IDENTITY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A single triangle without normals, referenced by two nodes of which
+    // the second is a translated child of the first.
+    const TRIANGLE: &str = r#"{
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [
+            { "name": "parent", "mesh": 0, "translation": [1.0, 0.0, 0.0], "children": [1] },
+            { "name": "parent", "mesh": 0, "translation": [0.0, 2.0, 0.0] }
+        ],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 } }] }],
+        "accessors": [{
+            "bufferView": 0,
+            "componentType": 5126,
+            "count": 3,
+            "type": "VEC3",
+            "min": [0.0, 0.0, 0.0],
+            "max": [1.0, 1.0, 0.0]
+        }],
+        "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+        "buffers": [{
+            "byteLength": 36,
+            "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA"
+        }]
+    }"#;
+
+    // The triangle below a node whose mesh only has points.
+    const POINTS_PARENT: &str = r#"{
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [
+            { "name": "points", "mesh": 0, "translation": [1.0, 0.0, 0.0], "children": [1] },
+            { "name": "triangle", "mesh": 1, "translation": [0.0, 2.0, 0.0] }
+        ],
+        "meshes": [
+            { "primitives": [{ "attributes": { "POSITION": 0 }, "mode": 0 }] },
+            { "primitives": [{ "attributes": { "POSITION": 0 } }] }
+        ],
+        "accessors": [{
+            "bufferView": 0,
+            "componentType": 5126,
+            "count": 3,
+            "type": "VEC3",
+            "min": [0.0, 0.0, 0.0],
+            "max": [1.0, 1.0, 0.0]
+        }],
+        "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+        "buffers": [{
+            "byteLength": 36,
+            "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA"
+        }]
+    }"#;
+
+    #[test]
+    fn load_triangle() {
+        let (document, buffers, _images) = gltf::import_slice(TRIANGLE).unwrap();
+        let scene = load_document(&document, &buffers, None);
+        assert_eq!(scene.len(), 2);
+        let [parent, child] = ["parent", "parent#1"].map(|name| scene.find(name).unwrap());
+        let mesh = scene.get(parent).unwrap().mesh();
+        assert!(Arc::ptr_eq(mesh, scene.get(child).unwrap().mesh()));
+        assert_eq!(mesh.indices(), &[0, 1, 2]);
+        assert_eq!(mesh.normals()[0].normal, [0.0, 0.0, 1.0]);
+        assert_eq!(scene.parent(child), Some(parent));
+        let origin = scene
+            .world_matrix(child)
+            .unwrap()
+            .transform_point3(glam::Vec3::ZERO);
+        assert_eq!(origin, glam::Vec3::new(1.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn keep_children_of_points() {
+        let (document, buffers, _images) = gltf::import_slice(POINTS_PARENT).unwrap();
+        let scene = load_document(&document, &buffers, None);
+        assert_eq!(scene.len(), 1);
+        let triangle = scene.find("triangle").unwrap();
+        assert_eq!(scene.parent(triangle), None);
+        let origin = scene
+            .world_matrix(triangle)
+            .unwrap()
+            .transform_point3(glam::Vec3::ZERO);
+        assert_eq!(origin, glam::Vec3::new(1.0, 2.0, 0.0));
+    }
+}
@@ -0,0 +1,240 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::engine::capture::Frame;
+
+// Reference images are read from `tests/golden/<name>.png`. Setting this
+// environment variable writes the actual frame as new reference instead of
+// comparing, which is also the only way to create a missing one.
+pub const BLESS_VAR: &str = "MAGMA_BLESS";
+
+pub struct Comparison {
+    // Number of pixels with at least one channel outside of the tolerance.
+    pub mismatched_pixels: usize,
+    // Largest difference of a single channel over all pixels.
+    pub max_difference: u8,
+}
+
+// Compares two frames of equal size channel by channel.
+pub fn compare(actual: &Frame, expected: &Frame, tolerance: u8) -> Comparison {
+    assert_eq!(
+        (actual.width, actual.height),
+        (expected.width, expected.height),
+        "golden: frames differ in size"
+    );
+    let mut comparison = Comparison {
+        mismatched_pixels: 0,
+        max_difference: 0,
+    };
+    for (a, e) in actual
+        .pixels
+        .chunks_exact(4)
+        .zip(expected.pixels.chunks_exact(4))
+    {
+        let difference = pixel_difference(a, e);
+        comparison.max_difference = comparison.max_difference.max(difference);
+        if difference > tolerance {
+            comparison.mismatched_pixels += 1;
+        }
+    }
+    comparison
+}
+
+// Highlights mismatched pixels in red on top of a dimmed grayscale version of
+// the expected frame.
+pub fn diff_image(actual: &Frame, expected: &Frame, tolerance: u8) -> Frame {
+    let pixels = actual
+        .pixels
+        .chunks_exact(4)
+        .zip(expected.pixels.chunks_exact(4))
+        .flat_map(|(a, e)| {
+            if pixel_difference(a, e) > tolerance {
+                [255, 0, 0, 255]
+            } else {
+                let gray = ((e[0] as u16 + e[1] as u16 + e[2] as u16) / 9) as u8;
+                [gray, gray, gray, 255]
+            }
+        })
+        .collect();
+    Frame {
+        width: expected.width,
+        height: expected.height,
+        pixels,
+    }
+}
+
+// Checks a rendered frame against the reference image with the given name.
+// On mismatch the actual frame and a diff image are written next to each
+// other into `target/golden/`.
+pub fn check(name: &str, actual: &Frame, tolerance: u8) -> Result<(), String> {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let reference = manifest_dir
+        .join("tests/golden")
+        .join(format!("{name}.png"));
+    if env::var_os(BLESS_VAR).is_some() {
+        fs::create_dir_all(reference.parent().unwrap()).map_err(|e| e.to_string())?;
+        actual.save(&reference).map_err(|e| e.to_string())?;
+        println!("golden: wrote reference {}", reference.display());
+        return Ok(());
+    }
+    if !reference.exists() {
+        return Err(format!(
+            "golden: reference {} is missing, run with {BLESS_VAR}=1 to create it",
+            reference.display()
+        ));
+    }
+    let expected = load_png(&reference).map_err(|e| e.to_string())?;
+    let output_dir = manifest_dir.join("target/golden");
+    fs::create_dir_all(&output_dir).map_err(|e| e.to_string())?;
+    let actual_path = output_dir.join(format!("{name}.actual.png"));
+    if (actual.width, actual.height) != (expected.width, expected.height) {
+        actual.save(&actual_path).map_err(|e| e.to_string())?;
+        return Err(format!(
+            "golden: {name} is {}x{} but reference is {}x{}, actual frame written to {}",
+            actual.width,
+            actual.height,
+            expected.width,
+            expected.height,
+            actual_path.display()
+        ));
+    }
+    let comparison = compare(actual, &expected, tolerance);
+    if comparison.mismatched_pixels == 0 {
+        return Ok(());
+    }
+    let diff_path = output_dir.join(format!("{name}.diff.png"));
+    actual.save(&actual_path).map_err(|e| e.to_string())?;
+    diff_image(actual, &expected, tolerance)
+        .save(&diff_path)
+        .map_err(|e| e.to_string())?;
+    Err(format!(
+        "golden: {name} has {} mismatched pixels (max difference {}), diff written to {}",
+        comparison.mismatched_pixels,
+        comparison.max_difference,
+        diff_path.display()
+    ))
+}
+
+// Decodes a PNG of any 8 bit color type into RGBA8.
+pub fn load_png(path: &Path) -> io::Result<Frame> {
+    let data = fs::read(path)?;
+    let mut decoder = png::Decoder::new(data.as_slice());
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    buffer.truncate(info.buffer_size());
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Rgb => buffer
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buffer
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => buffer.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        png::ColorType::Indexed => unreachable!("indexed colors are expanded by the decoder"),
+    };
+    Ok(Frame {
+        width: info.width,
+        height: info.height,
+        pixels,
+    })
+}
+
+fn pixel_difference(a: &[u8], b: &[u8]) -> u8 {
+    a.iter().zip(b).map(|(a, b)| a.abs_diff(*b)).max().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::debug::{DebugSeverity, create_debug_instance};
+    use crate::engine::device::DeviceSelection;
+    use crate::engine::scene;
+    use crate::engine::{Engine, EngineConfig};
+    use std::sync::Arc;
+    use vulkano::instance::debug::DebugUtilsMessenger;
+    use vulkano::instance::{Instance, InstanceExtensions};
+
+    const EXTENT: [u32; 2] = [320, 240];
+    const TOLERANCE: u8 = 2;
+
+    // Golden tests are skipped on machines without any Vulkan device. Where
+    // the validation layer is installed, its warnings are printed.
+    fn create_instance() -> Option<(Arc<Instance>, Option<DebugUtilsMessenger>)> {
+        let (instance, debug_messenger) =
+            create_debug_instance(InstanceExtensions::empty(), DebugSeverity::Warning).ok()?;
+        let has_device = instance.enumerate_physical_devices().ok()?.len() > 0;
+        if !has_device {
+            return None;
+        }
+        Some((instance, debug_messenger))
+    }
+
+    fn frame(pixels: &[[u8; 4]]) -> Frame {
+        Frame {
+            width: pixels.len() as u32,
+            height: 1,
+            pixels: pixels.concat(),
+        }
+    }
+
+    #[test]
+    fn compare_respects_tolerance() {
+        let expected = frame(&[[10, 10, 10, 255], [200, 0, 0, 255]]);
+        let actual = frame(&[[12, 10, 10, 255], [200, 0, 9, 255]]);
+        let comparison = compare(&actual, &expected, 2);
+        assert_eq!(comparison.mismatched_pixels, 1);
+        assert_eq!(comparison.max_difference, 9);
+        let diff = diff_image(&actual, &expected, 2);
+        assert_eq!(&diff.pixels[4..], &[255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn missing_reference_fails() {
+        if env::var_os(BLESS_VAR).is_some() {
+            return;
+        }
+        let actual = frame(&[[0, 0, 0, 255]]);
+        let error = check("missing_reference", &actual, TOLERANCE).unwrap_err();
+        assert!(error.contains(BLESS_VAR));
+    }
+
+    // The device can be pinned with `MAGMA_DEVICE`.
+    fn config() -> EngineConfig {
+        EngineConfig {
+            device: DeviceSelection::from_env(),
+            ..EngineConfig::default()
+        }
+    }
+
+    #[test]
+    fn cube_scene() {
+        let Some((instance, _debug_messenger)) = create_instance() else {
+            println!("golden: no Vulkan device available, skipping");
+            return;
+        };
+        let mut engine = Engine::new_headless(&instance, EXTENT, &config()).unwrap();
+        engine.set_scene(scene::get_cube_scene());
+        let frame = engine.capture_frame().unwrap().unwrap();
+        check("cube_scene", &frame, TOLERANCE).unwrap();
+    }
+
+    #[test]
+    fn primitives_scene() {
+        let Some((instance, _debug_messenger)) = create_instance() else {
+            println!("golden: no Vulkan device available, skipping");
+            return;
+        };
+        let mut engine = Engine::new_headless(&instance, EXTENT, &config()).unwrap();
+        engine.set_scene(scene::get_primitives_scene());
+        let frame = engine.capture_frame().unwrap().unwrap();
+        check("primitives_scene", &frame, TOLERANCE).unwrap();
+    }
+}
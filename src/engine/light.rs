@@ -0,0 +1,103 @@
+use glam::{Quat, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::transform::Transform;
+
+// Lights of a scene beyond this number are ignored, must match the fragment
+// shader.
+pub const MAX_LIGHTS: usize = 16;
+
+// Light reaching every surface from all directions, so parts of the scene
+// facing away from all lights are not black.
+pub const DEFAULT_AMBIENT_LIGHT: [f32; 3] = [0.1, 0.1, 0.1];
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LightKind {
+    // Infinitely far away, like the sun. Only the rotation of the transform
+    // matters.
+    Directional,
+    // Shines in all directions. Only the translation of the transform
+    // matters.
+    Point,
+    // Shines in a cone around the forward direction of the transform. Angles
+    // are in radians between the axis and the edge of the fully lit inner
+    // cone and of the outer cone, outside of which the light has no effect.
+    Spot { inner_angle: f32, outer_angle: f32 },
+}
+
+// Directional and spot lights shine along the local -z axis of their
+// transform, like cameras look. Scale is ignored.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    pub transform: Transform,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    // Constant, linear and quadratic coefficients of the falloff
+    // `1 / (c + l * d + q * d * d)` with the distance `d` of point and spot
+    // lights. The default has no falloff.
+    pub attenuation: [f32; 3],
+    // Whether models block the light, which is only supported by
+    // directional and spot lights.
+    pub cast_shadows: bool,
+}
+
+impl Light {
+    pub const DEFAULT: Light = Light {
+        kind: LightKind::Point,
+        transform: Transform::IDENTITY,
+        color: [1.0, 1.0, 1.0],
+        intensity: 1.0,
+        attenuation: [1.0, 0.0, 0.0],
+        cast_shadows: false,
+    };
+
+    pub fn directional(direction: Vec3) -> Self {
+        Light {
+            kind: LightKind::Directional,
+            transform: Transform {
+                rotation: rotation_towards(direction),
+                ..Transform::IDENTITY
+            },
+            ..Light::DEFAULT
+        }
+    }
+
+    pub fn point(position: Vec3) -> Self {
+        Light {
+            kind: LightKind::Point,
+            transform: Transform::from_translation(position),
+            ..Light::DEFAULT
+        }
+    }
+
+    pub fn spot(position: Vec3, direction: Vec3, inner_angle: f32, outer_angle: f32) -> Self {
+        Light {
+            kind: LightKind::Spot {
+                inner_angle,
+                outer_angle,
+            },
+            transform: Transform {
+                translation: position,
+                rotation: rotation_towards(direction),
+                ..Transform::IDENTITY
+            },
+            ..Light::DEFAULT
+        }
+    }
+
+    pub fn direction(&self) -> Vec3 {
+        self.transform.forward()
+    }
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        Light::DEFAULT
+    }
+}
+
+// Rotation that turns the -z axis towards `direction`.
+pub fn rotation_towards(direction: Vec3) -> Quat {
+    Quat::from_rotation_arc(Vec3::NEG_Z, direction.normalize_or(Vec3::NEG_Z))
+}
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+use crate::engine::model::DEFAULT_COLOR;
+
+// How the surface of a model looks. Omitted fields in scene files take the
+// values of `Material::DEFAULT`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Material {
+    pub base_color: [f32; 3],
+    // Exponent of the specular highlight, larger values give smaller and
+    // sharper highlights. Zero disables the highlight.
+    pub shininess: f32,
+    // Light emitted by the surface itself, added regardless of lighting.
+    pub emissive: [f32; 3],
+}
+
+impl Material {
+    pub const DEFAULT: Material = Material {
+        base_color: DEFAULT_COLOR,
+        shininess: 0.0,
+        emissive: [0.0; 3],
+    };
+
+    pub fn from_color(base_color: [f32; 3]) -> Self {
+        Material {
+            base_color,
+            ..Material::DEFAULT
+        }
+    }
+
+    // Equal for equal materials, so draws can be batched by material.
+    pub(crate) fn key(&self) -> [u32; 7] {
+        let [r, g, b] = self.base_color.map(f32::to_bits);
+        let [er, eg, eb] = self.emissive.map(f32::to_bits);
+        [r, g, b, self.shininess.to_bits(), er, eg, eb]
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material::DEFAULT
+    }
+}
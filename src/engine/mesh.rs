@@ -0,0 +1,165 @@
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use vulkano::buffer::BufferContents;
+use vulkano::pipeline::graphics::vertex_input::Vertex;
+
+#[derive(BufferContents, Vertex, Clone)]
+#[repr(C)]
+pub struct Position {
+    #[format(R32G32B32_SFLOAT)]
+    pub position: [f32; 3],
+}
+
+#[derive(BufferContents, Vertex, Clone)]
+#[repr(C)]
+pub struct Normal {
+    #[format(R32G32B32_SFLOAT)]
+    pub normal: [f32; 3],
+}
+
+// Texture coordinates with the origin at the top left corner of the texture.
+#[derive(BufferContents, Vertex, Clone)]
+#[repr(C)]
+pub struct Uv {
+    #[format(R32G32_SFLOAT)]
+    pub uv: [f32; 2],
+}
+
+// Where the geometry of a mesh comes from. Scene files store the source
+// instead of the geometry. Files are referenced by object or mesh and
+// primitive index, since names in OBJ and glTF files need not be unique.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MeshSource {
+    Cube,
+    UvSphere {
+        segments: u32,
+        rings: u32,
+    },
+    Icosphere {
+        subdivisions: u32,
+    },
+    Plane {
+        subdivisions: u32,
+    },
+    Cylinder {
+        segments: u32,
+    },
+    Cone {
+        segments: u32,
+    },
+    Torus {
+        ring_segments: u32,
+        tube_segments: u32,
+    },
+    Capsule {
+        segments: u32,
+        rings: u32,
+    },
+    Obj {
+        path: PathBuf,
+        object: usize,
+    },
+    Gltf {
+        path: PathBuf,
+        mesh: usize,
+        primitive: usize,
+    },
+}
+
+// Immutable geometry that is shared between models through an `Arc`, so it
+// is stored and uploaded to the GPU only once.
+pub struct Mesh {
+    id: u64,
+    source: Option<MeshSource>,
+    positions: Vec<Position>,
+    normals: Vec<Normal>,
+    uvs: Vec<Uv>,
+    indices: Vec<u32>,
+    radius: f32,
+}
+
+impl Mesh {
+    // All texture coordinates are zero until set with `with_uvs`.
+    pub fn new(positions: Vec<Position>, normals: Vec<Normal>, indices: Vec<u32>) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let radius = positions
+            .iter()
+            .map(|p| Vec3::from(p.position).length())
+            .fold(0.0, f32::max);
+        Mesh {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            source: None,
+            uvs: vec![Uv { uv: [0.0; 2] }; positions.len()],
+            radius,
+            positions,
+            normals,
+            indices,
+        }
+    }
+
+    // Unique for every mesh, used to look up its GPU buffers.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn with_source(mut self, source: MeshSource) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn with_uvs(mut self, uvs: Vec<Uv>) -> Self {
+        assert_eq!(
+            uvs.len(),
+            self.positions.len(),
+            "engine: a mesh needs one texture coordinate per vertex"
+        );
+        self.uvs = uvs;
+        self
+    }
+
+    pub fn source(&self) -> Option<&MeshSource> {
+        self.source.as_ref()
+    }
+
+    pub fn positions(&self) -> &[Position] {
+        &self.positions
+    }
+
+    pub fn normals(&self) -> &[Normal] {
+        &self.normals
+    }
+
+    pub fn uvs(&self) -> &[Uv] {
+        &self.uvs
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    // Distance of the farthest vertex from the origin.
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+}
+
+// Smooth vertex normals, averaged over the faces adjacent to each vertex and
+// weighted by face area. Used for imported meshes without normals.
+pub fn compute_normals(positions: &[Position], indices: &[u32]) -> Vec<Normal> {
+    let mut normals = vec![Vec3::ZERO; positions.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(positions[triangle[i] as usize].position));
+        let face_normal = (b - a).cross(c - a);
+        for &i in triangle {
+            normals[i as usize] += face_normal;
+        }
+    }
+    normals
+        .into_iter()
+        .map(|n| Normal {
+            normal: n.normalize_or_zero().into(),
+        })
+        .collect()
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+use vulkano::buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CopyBufferInfo, PrimaryAutoCommandBuffer};
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator};
+
+use crate::engine::error::MagmaError;
+use crate::engine::mesh::{Mesh, Normal, Position, Uv};
+
+// Geometry of a mesh in device local memory.
+#[derive(Clone)]
+pub struct GpuMesh {
+    pub positions: Subbuffer<[Position]>,
+    pub normals: Subbuffer<[Normal]>,
+    pub uvs: Subbuffer<[Uv]>,
+    pub indices: Subbuffer<[u32]>,
+}
+
+// Keeps uploaded geometry alive across frames, keyed by mesh id, so buffers
+// are only created once per mesh no matter how many models share it.
+pub struct MeshCache {
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    meshes: HashMap<u64, GpuMesh>,
+}
+
+impl MeshCache {
+    pub fn new(memory_allocator: Arc<StandardMemoryAllocator>) -> Self {
+        MeshCache {
+            memory_allocator,
+            meshes: HashMap::new(),
+        }
+    }
+
+    // Returns the GPU buffers of the mesh. On a cache miss the upload is
+    // recorded into `builder`, which therefore must not be inside a render pass.
+    pub fn get_or_upload(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        mesh: &Mesh,
+    ) -> Result<GpuMesh, MagmaError> {
+        if let Some(gpu_mesh) = self.meshes.get(&mesh.id()) {
+            return Ok(gpu_mesh.clone());
+        }
+        let gpu_mesh = GpuMesh {
+            positions: upload(
+                &self.memory_allocator,
+                builder,
+                BufferUsage::VERTEX_BUFFER,
+                mesh.positions(),
+            )?,
+            normals: upload(
+                &self.memory_allocator,
+                builder,
+                BufferUsage::VERTEX_BUFFER,
+                mesh.normals(),
+            )?,
+            uvs: upload(
+                &self.memory_allocator,
+                builder,
+                BufferUsage::VERTEX_BUFFER,
+                mesh.uvs(),
+            )?,
+            indices: upload(
+                &self.memory_allocator,
+                builder,
+                BufferUsage::INDEX_BUFFER,
+                mesh.indices(),
+            )?,
+        };
+        self.meshes.insert(mesh.id(), gpu_mesh.clone());
+        Ok(gpu_mesh)
+    }
+
+    // Drops the buffers of all meshes that are no longer in use. Buffers still
+    // referenced by pending command buffers stay alive until those finish.
+    pub fn retain(&mut self, mesh_ids: &HashSet<u64>) {
+        self.meshes.retain(|id, _| mesh_ids.contains(id));
+    }
+}
+
+// Copies the data through a host visible staging buffer into a device local
+// buffer.
+fn upload<T: BufferContents + Clone>(
+    memory_allocator: &Arc<StandardMemoryAllocator>,
+    builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    usage: BufferUsage,
+    data: &[T],
+) -> Result<Subbuffer<[T]>, MagmaError> {
+    let staging_buffer = Buffer::from_iter(
+        memory_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+            ..Default::default()
+        },
+        data.iter().cloned(),
+    )?;
+    let buffer = Buffer::new_slice::<T>(
+        memory_allocator.clone(),
+        BufferCreateInfo {
+            usage: usage | BufferUsage::TRANSFER_DST,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+            ..Default::default()
+        },
+        data.len() as u64,
+    )?;
+    builder.copy_buffer(CopyBufferInfo::buffers(staging_buffer, buffer.clone()))?;
+    Ok(buffer)
+}
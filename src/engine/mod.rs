@@ -1,30 +1,41 @@
-use glam::Vec3;
+use glam::Mat4;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use vulkano::Validated;
 use vulkano::VulkanError;
+use vulkano::VulkanLibrary;
+use vulkano::buffer::Buffer;
+use vulkano::buffer::BufferCreateInfo;
 use vulkano::buffer::BufferUsage;
-use vulkano::buffer::allocator::SubbufferAllocator;
-use vulkano::buffer::allocator::SubbufferAllocatorCreateInfo;
+use vulkano::buffer::Subbuffer;
 use vulkano::command_buffer::AutoCommandBufferBuilder;
 use vulkano::command_buffer::CommandBufferUsage;
+use vulkano::command_buffer::CopyImageToBufferInfo;
 use vulkano::command_buffer::PrimaryAutoCommandBuffer;
 use vulkano::command_buffer::RenderPassBeginInfo;
-use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
 use vulkano::descriptor_set::DescriptorSet;
 use vulkano::descriptor_set::WriteDescriptorSet;
 use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
 use vulkano::device::Device;
 use vulkano::device::Queue;
 use vulkano::format::Format;
+use vulkano::format::NumericFormat;
 use vulkano::image::Image;
 use vulkano::image::ImageCreateInfo;
 use vulkano::image::ImageType;
 use vulkano::image::ImageUsage;
+use vulkano::image::sampler::Sampler;
+use vulkano::image::sampler::SamplerCreateInfo;
 use vulkano::image::view::ImageView;
 use vulkano::instance::Instance;
+use vulkano::instance::InstanceCreateFlags;
+use vulkano::instance::InstanceCreateInfo;
+use vulkano::instance::InstanceExtensions;
 use vulkano::memory::allocator::AllocationCreateInfo;
 use vulkano::memory::allocator::MemoryTypeFilter;
 use vulkano::memory::allocator::StandardMemoryAllocator;
+use vulkano::pipeline::DynamicState;
 use vulkano::pipeline::GraphicsPipeline;
 use vulkano::pipeline::Pipeline;
 use vulkano::pipeline::PipelineBindPoint;
@@ -40,6 +51,7 @@ use vulkano::pipeline::graphics::multisample::MultisampleState;
 use vulkano::pipeline::graphics::rasterization::RasterizationState;
 use vulkano::pipeline::graphics::vertex_input::Vertex;
 use vulkano::pipeline::graphics::vertex_input::VertexDefinition;
+use vulkano::pipeline::graphics::viewport::Scissor;
 use vulkano::pipeline::graphics::viewport::Viewport;
 use vulkano::pipeline::graphics::viewport::ViewportState;
 use vulkano::pipeline::layout::PipelineDescriptorSetLayoutCreateInfo;
@@ -48,369 +60,905 @@ use vulkano::render_pass::FramebufferCreateInfo;
 use vulkano::render_pass::RenderPass;
 use vulkano::render_pass::Subpass;
 use vulkano::shader::EntryPoint;
+use vulkano::shader::ShaderModule;
+use vulkano::shader::SpecializationConstant;
+use vulkano::swapchain::PresentMode;
 use vulkano::swapchain::Surface;
 use vulkano::swapchain::Swapchain;
-use vulkano::swapchain::SwapchainCreateInfo;
 use vulkano::swapchain::SwapchainPresentInfo;
 use vulkano::swapchain::acquire_next_image;
 use vulkano::sync::{self, GpuFuture};
 use winit::dpi::PhysicalSize;
 use winit::window::Window;
 
+use crate::engine::device::DeviceSelection;
+use crate::engine::error::MagmaError;
+use crate::engine::frame::DEFAULT_FRAMES_IN_FLIGHT;
+use crate::engine::frame::FrameResources;
+use crate::engine::light::Light;
+use crate::engine::light::LightKind;
+use crate::engine::light::MAX_LIGHTS;
+use crate::engine::material::Material;
+use crate::engine::mesh_cache::GpuMesh;
+use crate::engine::mesh_cache::MeshCache;
+use crate::engine::model::InstanceData;
 use crate::engine::scene::Scene;
+use crate::engine::shadow::MAX_SHADOW_MAPS;
+use crate::engine::shadow::ShadowPass;
+use crate::engine::target::RenderTarget;
+use crate::engine::target::SwapchainConfig;
+use crate::engine::texture::Texture;
+use crate::engine::texture_cache::TextureCache;
 
-mod camera;
-mod device;
-mod model;
-mod scene;
+pub mod camera;
+pub mod capture;
+pub mod debug;
+pub mod device;
+pub mod error;
+mod frame;
+pub mod gltf_loader;
+#[cfg(test)]
+mod golden;
+pub mod light;
+pub mod material;
+pub mod mesh;
+mod mesh_cache;
+pub mod model;
+pub mod obj;
+pub mod scene;
+pub mod scene_file;
 mod shader;
+mod shadow;
+pub mod target;
+pub mod texture;
+mod texture_cache;
+pub mod transform;
+
+// Creates a Vulkan instance with the given extensions, e.g. those required
+// by `Surface::required_extensions`, or none for headless rendering.
+pub fn create_instance(
+    enabled_extensions: InstanceExtensions,
+) -> Result<Arc<Instance>, MagmaError> {
+    new_instance(VulkanLibrary::new()?, enabled_extensions, Vec::new())
+}
+
+fn new_instance(
+    library: Arc<VulkanLibrary>,
+    enabled_extensions: InstanceExtensions,
+    enabled_layers: Vec<String>,
+) -> Result<Arc<Instance>, MagmaError> {
+    let instance = Instance::new(
+        library,
+        InstanceCreateInfo {
+            flags: InstanceCreateFlags::ENUMERATE_PORTABILITY,
+            enabled_extensions,
+            enabled_layers,
+            ..Default::default()
+        },
+    )?;
+    Ok(instance)
+}
 
 pub struct Engine {
     device: Arc<Device>,
     queue: Arc<Queue>,
     memory_allocator: Arc<StandardMemoryAllocator>,
     descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
-    command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
-    uniform_buffer_allocator: SubbufferAllocator,
-    window: Arc<Window>,
-    swapchain: Arc<Swapchain>,
+    frames: Vec<FrameResources>,
+    // Index into `frames` of the next frame to record.
+    frame_index: usize,
+    mesh_cache: MeshCache,
+    texture_cache: TextureCache,
+    sampler: Arc<Sampler>,
+    // Bound for models without texture.
+    white_texture: Arc<Texture>,
+    shadow_pass: ShadowPass,
+    target: RenderTarget,
     render_pass: Arc<RenderPass>,
     vertex_shader: EntryPoint,
     fragment_shader: EntryPoint,
     framebuffers: Vec<Arc<Framebuffer>>,
     pipeline: Arc<GraphicsPipeline>,
-    previous_frame_end: Option<Box<dyn GpuFuture>>,
     recreate_swapchain: bool,
-    camera: camera::Camera,
+    swapchain_config: SwapchainConfig,
     scene: scene::Scene,
+    update: Option<Box<UpdateFn>>,
+    fixed_timestep: Option<Duration>,
+    // Measured time not yet simulated by fixed steps.
+    accumulated_time: Duration,
+    last_update: Option<Instant>,
+}
+
+// Instances drawn with one call, see `record_frame`.
+struct Batch {
+    mesh: GpuMesh,
+    material: Material,
+    texture: Arc<ImageView>,
+    instances: Vec<InstanceData>,
+}
+
+// Settings of a new engine. The swapchain and the frames in flight can be
+// changed later, see `set_swapchain_config` and `set_frames_in_flight`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EngineConfig {
+    pub device: DeviceSelection,
+    // Ignored by headless engines.
+    pub swapchain: SwapchainConfig,
+    pub frames_in_flight: usize,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        EngineConfig {
+            device: DeviceSelection::Auto,
+            swapchain: SwapchainConfig::default(),
+            frames_in_flight: DEFAULT_FRAMES_IN_FLIGHT,
+        }
+    }
 }
 
+// Called with the scene and the time step in seconds, see `set_update`.
+type UpdateFn = dyn FnMut(&mut Scene, f32);
+
+// Upper limit of fixed steps per frame, so a slow frame does not cause even
+// more steps in the next one.
+const MAX_FIXED_STEPS: u32 = 8;
+
 impl Engine {
-    pub fn new(instance: &Arc<Instance>, window: Arc<Window>) -> Self {
-        let surface = Surface::from_window(instance.clone(), window.clone())
-            .expect("engine: surface could not be created");
-        let (_physical_device, device, queue) = device::init_device(instance, &surface);
+    pub fn new(
+        instance: &Arc<Instance>,
+        window: Arc<Window>,
+        config: &EngineConfig,
+    ) -> Result<Self, MagmaError> {
+        let surface = Surface::from_window(instance.clone(), window.clone())?;
+        let (device, queue) = device::init_device(instance, Some(&surface), &config.device)?;
+        let memory_allocator = Arc::new(StandardMemoryAllocator::new_default(device.clone()));
+        let (swapchain, images) = target::create_swapchain(
+            &device,
+            surface,
+            window.inner_size().into(),
+            config.swapchain,
+        )?;
+        Engine::from_target(
+            device,
+            queue,
+            memory_allocator,
+            RenderTarget::Window { window, swapchain },
+            &images,
+            config,
+        )
+    }
+
+    // Creates an engine without a window that renders into an offscreen image
+    // of the given extent. Frames are produced with `render_frame`.
+    pub fn new_headless(
+        instance: &Arc<Instance>,
+        extent: [u32; 2],
+        config: &EngineConfig,
+    ) -> Result<Self, MagmaError> {
+        let (device, queue) = device::init_device(instance, None, &config.device)?;
         let memory_allocator = Arc::new(StandardMemoryAllocator::new_default(device.clone()));
+        let image = target::create_offscreen_image(&memory_allocator, extent)?;
+        Engine::from_target(
+            device,
+            queue,
+            memory_allocator,
+            RenderTarget::Offscreen {
+                image: image.clone(),
+            },
+            &[image],
+            config,
+        )
+    }
+
+    fn from_target(
+        device: Arc<Device>,
+        queue: Arc<Queue>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        target: RenderTarget,
+        images: &[Arc<Image>],
+        config: &EngineConfig,
+    ) -> Result<Self, MagmaError> {
         let descriptor_set_allocator = Arc::new(StandardDescriptorSetAllocator::new(
             device.clone(),
             Default::default(),
         ));
-        let command_buffer_allocator = Arc::new(StandardCommandBufferAllocator::new(
-            device.clone(),
-            Default::default(),
-        ));
-        let uniform_buffer_allocator = SubbufferAllocator::new(
-            memory_allocator.clone(),
-            SubbufferAllocatorCreateInfo {
-                buffer_usage: BufferUsage::UNIFORM_BUFFER,
-                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
-                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
-                ..Default::default()
-            },
-        );
-        let window_size = window.inner_size();
-        let (swapchain, images) = {
-            let surface_capabilities = device
-                .physical_device()
-                .surface_capabilities(&surface, Default::default())
-                .unwrap();
-            let (image_format, _) = device
-                .physical_device()
-                .surface_formats(&surface, Default::default())
-                .unwrap()[0];
-            Swapchain::new(
-                device.clone(),
-                surface,
-                SwapchainCreateInfo {
-                    min_image_count: surface_capabilities.min_image_count.max(2),
-                    image_format,
-                    image_extent: window_size.into(),
-                    image_usage: ImageUsage::COLOR_ATTACHMENT,
-                    composite_alpha: surface_capabilities
-                        .supported_composite_alpha
-                        .into_iter()
-                        .next()
-                        .unwrap(),
-                    ..Default::default()
-                },
-            )
-            .unwrap()
-        };
-        let aspect_ratio = swapchain.image_extent()[0] as f32 / swapchain.image_extent()[1] as f32;
-        let camera = camera::Camera::new(aspect_ratio);
-        let render_pass = vulkano::single_pass_renderpass!(
-            device.clone(),
-            attachments: {
-                color: {
-                    format: swapchain.image_format(),
-                    samples: 1,
-                    load_op: Clear,
-                    store_op: Store,
-                },
-                depth_stencil: {
-                    format: Format::D16_UNORM,
-                    samples: 1,
-                    load_op: Clear,
-                    store_op: DontCare,
-                },
-            },
-            pass: {
-                color: [color],
-                depth_stencil: {depth_stencil},
-            },
-        )
-        .unwrap();
-        let framebuffers = create_framebuffers(&memory_allocator, &images, &render_pass);
-        let vertex_shader = shader::mesh_vs::load(device.clone())
-            .unwrap()
-            .entry_point("main")
-            .unwrap();
-        let fragment_shader = shader::mesh_fs::load(device.clone())
-            .unwrap()
-            .entry_point("main")
-            .unwrap();
+        if config.frames_in_flight == 0 {
+            return Err(MagmaError::NoFramesInFlight);
+        }
+        let frames = (0..config.frames_in_flight)
+            .map(|_| FrameResources::new(&device, &memory_allocator))
+            .collect();
+        let extent = target.extent();
+        let render_pass = create_render_pass(&device, target.format())?;
+        let framebuffers = create_framebuffers(&memory_allocator, images, &render_pass)?;
+        let vertex_shader = load_shader("mesh_vs", shader::mesh_vs::load(device.clone()))?;
+        let fragment_shader = load_shader("mesh_fs", shader::mesh_fs::load(device.clone()))?;
         let pipeline = create_pipeline(
             &device,
             &render_pass,
             vertex_shader.clone(),
             fragment_shader.clone(),
-            window_size,
-        );
-        let previous_frame_end = Some(sync::now(device.clone()).boxed());
+        )?;
+        let mesh_cache = MeshCache::new(memory_allocator.clone());
+        let texture_cache = TextureCache::new(memory_allocator.clone());
+        let sampler = Sampler::new(device.clone(), SamplerCreateInfo::simple_repeat_linear())?;
+        let shadow_pass = ShadowPass::new(&device, &memory_allocator)?;
         let mut scene = Scene::new();
-        let mut cube1 = model::get_cube();
-        cube1.translate(Vec3 {
-            x: 0.0,
-            y: 0.0,
-            z: -5.0,
-        });
-        cube1.rotate(0.0, -0.3, 0.0);
-        scene.models.insert("cube1".to_string(), cube1);
-        let mut cube2 = model::get_cube();
-        cube2.translate(Vec3 {
-            x: 3.0,
-            y: 0.0,
-            z: -5.0,
-        });
-        scene.models.insert("cube2".to_string(), cube2);
-        let mut cube3 = model::get_cube();
-        cube3.translate(Vec3 {
-            x: -3.0,
-            y: 0.0,
-            z: -5.0,
-        });
-        scene.models.insert("cube3".to_string(), cube3);
-        Engine {
-            device: device,
-            queue: queue,
-            memory_allocator: memory_allocator,
-            descriptor_set_allocator: descriptor_set_allocator,
-            command_buffer_allocator: command_buffer_allocator,
-            uniform_buffer_allocator: uniform_buffer_allocator,
-            window: window,
-            swapchain: swapchain,
-            render_pass: render_pass,
-            vertex_shader: vertex_shader,
-            fragment_shader: fragment_shader,
-            framebuffers: framebuffers,
-            pipeline: pipeline,
-            previous_frame_end: previous_frame_end,
+        scene
+            .camera
+            .update_projection(extent[0] as f32 / extent[1] as f32);
+        Ok(Engine {
+            device,
+            queue,
+            memory_allocator,
+            descriptor_set_allocator,
+            frames,
+            frame_index: 0,
+            mesh_cache,
+            texture_cache,
+            sampler,
+            white_texture: Arc::new(Texture::white()),
+            shadow_pass,
+            target,
+            render_pass,
+            vertex_shader,
+            fragment_shader,
+            framebuffers,
+            pipeline,
             recreate_swapchain: false,
-            camera: camera,
-            scene: scene,
+            swapchain_config: config.swapchain,
+            scene,
+            update: None,
+            fixed_timestep: None,
+            accumulated_time: Duration::ZERO,
+            last_update: None,
+        })
+    }
+
+    // Sets the function that is called once per frame before rendering, with
+    // the scene (including its camera) and the time step in seconds.
+    pub fn set_update(&mut self, update: impl FnMut(&mut Scene, f32) + 'static) {
+        self.update = Some(Box::new(update));
+    }
+
+    // With a fixed timestep the update function is called with that step as
+    // often as the measured time allows, i.e. zero or more times per frame,
+    // which makes the simulation independent of the framerate. `None` calls
+    // it exactly once per frame with the measured time since the last frame.
+    pub fn set_fixed_timestep(&mut self, timestep: Option<Duration>) {
+        self.fixed_timestep = timestep.filter(|timestep| !timestep.is_zero());
+        self.accumulated_time = Duration::ZERO;
+    }
+
+    fn update_scene(&mut self) {
+        let now = Instant::now();
+        let elapsed = self.last_update.map_or(Duration::ZERO, |last| now - last);
+        self.last_update = Some(now);
+        let Some(update) = &mut self.update else {
+            return;
+        };
+        let Some(timestep) = self.fixed_timestep else {
+            update(&mut self.scene, elapsed.as_secs_f32());
+            return;
+        };
+        self.accumulated_time += elapsed;
+        for _ in 0..take_fixed_steps(&mut self.accumulated_time, timestep) {
+            update(&mut self.scene, timestep.as_secs_f32());
         }
     }
 
-    pub fn draw(&mut self) {
-        let window_size = self.window.inner_size();
+    // Skipping a frame, e.g. while the window is minimized, is not an error.
+    pub fn draw(&mut self) -> Result<(), MagmaError> {
+        self.draw_frame(false)?;
+        Ok(())
+    }
+
+    // Renders a frame like `draw` and reads its color attachment back to the
+    // CPU. Returns `None` if no frame could be rendered, e.g. because the
+    // window is minimized or the swapchain is out of date.
+    pub fn capture_frame(&mut self) -> Result<Option<capture::Frame>, MagmaError> {
+        self.draw_frame(true)
+    }
+
+    // Records, submits and (in window mode) presents one frame. When capturing,
+    // the call blocks until the GPU has finished and returns the frame.
+    fn draw_frame(&mut self, capture: bool) -> Result<Option<capture::Frame>, MagmaError> {
+        self.update_scene();
+        let (window, swapchain) = match &self.target {
+            RenderTarget::Window { window, swapchain } => (window.clone(), swapchain.clone()),
+            RenderTarget::Offscreen { .. } => return self.render_offscreen(capture),
+        };
+        let window_size = window.inner_size();
         if window_size.width == 0 || window_size.height == 0 {
-            return;
+            return Ok(None);
         }
-        self.previous_frame_end.as_mut().unwrap().cleanup_finished();
-        if self.recreate_swapchain {
-            self.update_window_size(window_size);
+        self.frames[self.frame_index].wait()?;
+        let swapchain = if self.recreate_swapchain {
+            self.rebuild_swapchain(window_size)?
+        } else {
+            swapchain
+        };
+        // Checked before acquiring, since an acquired image that is never
+        // presented is lost for good.
+        if capture && !swapchain.image_usage().intersects(ImageUsage::TRANSFER_SRC) {
+            return Err(MagmaError::CaptureUnsupported(
+                "surface does not allow reading back images".to_string(),
+            ));
+        }
+        if capture && !capture::supports_format(swapchain.image_format()) {
+            return Err(MagmaError::CaptureUnsupported(format!(
+                "format {:?}",
+                swapchain.image_format()
+            )));
         }
         let (image_index, suboptimal, acquire_future) =
-            match acquire_next_image(self.swapchain.clone(), None).map_err(Validated::unwrap) {
+            match acquire_next_image(swapchain.clone(), None).map_err(Validated::unwrap) {
                 Ok(r) => r,
                 Err(VulkanError::OutOfDate) => {
                     self.recreate_swapchain = true;
-                    return;
+                    return Ok(None);
                 }
-                Err(e) => panic!("engine: failed to acquire next image: {e}"),
+                Err(e) => return Err(e.into()),
             };
         if suboptimal {
             self.recreate_swapchain = true;
         }
-        let mut builder = AutoCommandBufferBuilder::primary(
-            self.command_buffer_allocator.clone(),
-            self.queue.queue_family_index(),
-            CommandBufferUsage::OneTimeSubmit,
-        )
-        .unwrap();
-        builder
-            .begin_render_pass(
-                RenderPassBeginInfo {
-                    clear_values: vec![Some([0.0, 0.0, 1.0, 1.0].into()), Some(1f32.into())],
-                    ..RenderPassBeginInfo::framebuffer(
-                        self.framebuffers[image_index as usize].clone(),
-                    )
-                },
-                Default::default(),
-            )
-            .unwrap()
-            .bind_pipeline_graphics(self.pipeline.clone())
-            .unwrap();
-        self.scene
-            .models
-            .get_mut("cube1")
-            .unwrap()
-            .rotate(-0.1, 0.0, 0.0);
-        self.scene
-            .models
-            .get_mut("cube2")
-            .unwrap()
-            .rotate(0.0, 0.0, 0.1);
-        for (_key, model) in &self.scene.models {
-            self.draw_model(&mut builder, &model);
-        }
-        builder.end_render_pass(Default::default()).unwrap();
-        let command_buffer = builder.build().unwrap();
+        let capture_buffer = capture.then(|| self.create_capture_buffer()).transpose()?;
+        let command_buffer = self.record_frame(image_index as usize, capture_buffer.clone())?;
         let future = self
-            .previous_frame_end
-            .take()
-            .unwrap()
+            .previous_frame_end()
             .join(acquire_future)
-            .then_execute(self.queue.clone(), command_buffer)
-            .unwrap()
+            .then_execute(self.queue.clone(), command_buffer)?
             .then_swapchain_present(
                 self.queue.clone(),
-                SwapchainPresentInfo::swapchain_image_index(self.swapchain.clone(), image_index),
+                SwapchainPresentInfo::swapchain_image_index(swapchain, image_index),
             )
+            .boxed_send_sync()
             .then_signal_fence_and_flush();
-        match future.map_err(Validated::unwrap) {
-            Ok(future) => {
-                self.previous_frame_end = Some(future.boxed());
-            }
+        let fence = match future.map_err(Validated::unwrap) {
+            Ok(future) => Some(Arc::new(future)),
             Err(VulkanError::OutOfDate) => {
                 self.recreate_swapchain = true;
-                self.previous_frame_end = Some(sync::now(self.device.clone()).boxed());
+                None
             }
-            Err(e) => {
-                println!("engine: failed to flush future: {e}");
-                self.previous_frame_end = Some(sync::now(self.device.clone()).boxed());
+            Err(e) => return Err(e.into()),
+        };
+        self.frames[self.frame_index].fence = fence.clone();
+        self.frame_index = (self.frame_index + 1) % self.frames.len();
+        window.request_redraw();
+        match (fence, capture_buffer) {
+            (Some(fence), Some(buffer)) => {
+                fence.wait(None)?;
+                self.read_capture_buffer(buffer).map(Some)
             }
+            _ => Ok(None),
         }
-        self.window.request_redraw();
     }
 
-    fn draw_model(
+    // Future of the most recently submitted frame, which the next submission
+    // has to follow.
+    fn previous_frame_end(&self) -> Box<dyn GpuFuture + Send + Sync> {
+        let previous = (self.frame_index + self.frames.len() - 1) % self.frames.len();
+        match &self.frames[previous].fence {
+            Some(fence) => fence.clone().boxed_send_sync(),
+            None => sync::now(self.device.clone()).boxed_send_sync(),
+        }
+    }
+
+    pub fn frames_in_flight(&self) -> usize {
+        self.frames.len()
+    }
+
+    // With more frames in flight the CPU can record further ahead of the GPU,
+    // at the cost of latency and memory. Waits for all pending frames.
+    pub fn set_frames_in_flight(&mut self, count: usize) -> Result<(), MagmaError> {
+        if count == 0 {
+            return Err(MagmaError::NoFramesInFlight);
+        }
+        for frame in &mut self.frames {
+            frame.wait()?;
+        }
+        self.frames = (0..count)
+            .map(|_| FrameResources::new(&self.device, &self.memory_allocator))
+            .collect();
+        self.frame_index = 0;
+        Ok(())
+    }
+
+    // Renders one frame into the offscreen image of a headless engine and
+    // blocks until the GPU has finished, so the image can be read right away.
+    pub fn render_frame(&mut self) -> Result<Arc<Image>, MagmaError> {
+        let RenderTarget::Offscreen { image } = &self.target else {
+            return Err(MagmaError::NotHeadless);
+        };
+        let image = image.clone();
+        self.update_scene();
+        self.render_offscreen(false)?;
+        Ok(image)
+    }
+
+    fn render_offscreen(&mut self, capture: bool) -> Result<Option<capture::Frame>, MagmaError> {
+        if let RenderTarget::Window { .. } = self.target {
+            return Err(MagmaError::NotHeadless);
+        }
+        // There is a single target image, so frames are not overlapped.
+        self.frames[self.frame_index].wait()?;
+        let capture_buffer = capture.then(|| self.create_capture_buffer()).transpose()?;
+        let command_buffer = self.record_frame(0, capture_buffer.clone())?;
+        self.previous_frame_end()
+            .then_execute(self.queue.clone(), command_buffer)?
+            .then_signal_fence_and_flush()?
+            .wait(None)?;
+        self.frame_index = (self.frame_index + 1) % self.frames.len();
+        capture_buffer
+            .map(|buffer| self.read_capture_buffer(buffer))
+            .transpose()
+    }
+
+    // Host visible buffer that can hold one color attachment of the target.
+    fn create_capture_buffer(&self) -> Result<Subbuffer<[u8]>, MagmaError> {
+        let [width, height] = self.target.extent();
+        let buffer = Buffer::new_slice::<u8>(
+            self.memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+                ..Default::default()
+            },
+            width as u64 * height as u64 * self.target.format().block_size(),
+        )?;
+        Ok(buffer)
+    }
+
+    fn read_capture_buffer(&self, buffer: Subbuffer<[u8]>) -> Result<capture::Frame, MagmaError> {
+        let [width, height] = self.target.extent();
+        let data = buffer.read()?.to_vec();
+        capture::Frame::from_raw(width, height, self.target.format(), data)
+    }
+
+    fn record_frame(
+        &mut self,
+        framebuffer_index: usize,
+        capture: Option<Subbuffer<[u8]>>,
+    ) -> Result<Arc<PrimaryAutoCommandBuffer>, MagmaError> {
+        let framebuffer = self.framebuffers[framebuffer_index].clone();
+        let mut builder = AutoCommandBufferBuilder::primary(
+            self.frames[self.frame_index]
+                .command_buffer_allocator
+                .clone(),
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        // Models sharing a mesh, material and texture are batched into one
+        // instanced draw. Uploads of new geometry and textures have to be
+        // recorded outside the render pass.
+        let mut batches: Vec<Batch> = Vec::new();
+        let mut batch_indices = HashMap::new();
+        let world_matrices = self.scene.world_matrices();
+        for (handle, model) in self.scene.iter() {
+            let texture = model.texture.as_ref().unwrap_or(&self.white_texture);
+            let key = (model.mesh().id(), model.material.key(), texture.id());
+            let index = match batch_indices.get(&key) {
+                Some(index) => *index,
+                None => {
+                    batches.push(Batch {
+                        mesh: self.mesh_cache.get_or_upload(&mut builder, model.mesh())?,
+                        material: model.material,
+                        texture: self.texture_cache.get_or_upload(&mut builder, texture)?,
+                        instances: Vec::new(),
+                    });
+                    batch_indices.insert(key, batches.len() - 1);
+                    batches.len() - 1
+                }
+            };
+            batches[index].instances.push(InstanceData {
+                world: world_matrices[&handle].to_cols_array_2d(),
+            });
+        }
+        self.mesh_cache
+            .retain(&batch_indices.keys().map(|(mesh_id, ..)| *mesh_id).collect());
+        self.texture_cache.retain(
+            &batch_indices
+                .keys()
+                .map(|(.., texture_id)| *texture_id)
+                .collect(),
+        );
+        let instance_buffers = batches
+            .iter()
+            .map(|batch| self.upload_instances(&batch.instances))
+            .collect::<Result<Vec<_>, _>>()?;
+        // The first shadow casting lights get a layer of the shadow map each,
+        // which is rendered before the scene.
+        let bounds = shadow::scene_bounds(&self.scene);
+        let mut shadows = Vec::new();
+        let lights = &self.scene.lights[..self.scene.lights.len().min(MAX_LIGHTS)];
+        let light_shadows = lights
+            .iter()
+            .map(|light| {
+                if shadows.len() == MAX_SHADOW_MAPS {
+                    return None;
+                }
+                let matrix = shadow::light_matrix(light, bounds?)?;
+                shadows.push((shadows.len() as u32, matrix));
+                shadows.last().copied()
+            })
+            .collect::<Vec<_>>();
+        if !shadows.is_empty() {
+            let draws = batches
+                .iter()
+                .zip(&instance_buffers)
+                .map(|(batch, instances)| (batch.mesh.clone(), instances.clone()))
+                .collect::<Vec<_>>();
+            self.shadow_pass.record(&mut builder, &shadows, &draws)?;
+        }
+        builder
+            .begin_render_pass(
+                RenderPassBeginInfo {
+                    clear_values: vec![Some([0.0, 0.0, 1.0, 1.0].into()), Some(1f32.into())],
+                    ..RenderPassBeginInfo::framebuffer(framebuffer.clone())
+                },
+                Default::default(),
+            )?
+            .bind_pipeline_graphics(self.pipeline.clone())?;
+        set_viewport(&mut builder, [0, 0], framebuffer.extent())?;
+        self.bind_scene(&mut builder, &light_shadows)?;
+        for (batch, instances) in batches.into_iter().zip(instance_buffers) {
+            self.draw_batch(&mut builder, batch, instances)?;
+        }
+        builder.end_render_pass(Default::default())?;
+        if let Some(buffer) = capture {
+            builder.copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(
+                framebuffer.attachments()[0].image().clone(),
+                buffer,
+            ))?;
+        }
+        Ok(builder.build()?)
+    }
+
+    // Copies the instances into a buffer of the current frame.
+    fn upload_instances(
+        &self,
+        instances: &[InstanceData],
+    ) -> Result<Subbuffer<[InstanceData]>, MagmaError> {
+        let buffer = self.frames[self.frame_index]
+            .instance_buffer_allocator
+            .allocate_slice(instances.len() as u64)?;
+        buffer.write()?.clone_from_slice(instances);
+        Ok(buffer)
+    }
+
+    // Binds the camera matrices, the lights, which are uploaded in view
+    // space, and the shadow maps. `light_shadows` holds the shadow map layer
+    // and matrix of every light that casts shadows this frame.
+    fn bind_scene(
         &self,
         builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
-        model: &model::Model,
-    ) {
-        let pos_buffer = model.create_vertex_buffer(&self.memory_allocator);
-        let normals_buffer = model.create_normals_buffer(&self.memory_allocator);
-        let index_buffer = model.create_index_buffer(&self.memory_allocator);
-        let index_buffer_length = index_buffer.len() as u32;
+        light_shadows: &[Option<(u32, Mat4)>],
+    ) -> Result<(), MagmaError> {
+        let view = self.scene.camera.view();
+        let uniform_allocator = &self.frames[self.frame_index].uniform_buffer_allocator;
         let uniform_buffer = {
             let uniform_data = shader::mesh_vs::Data {
-                world: model.get_model_matrix().to_cols_array_2d(),
-                view: self.camera.view.to_cols_array_2d(),
-                proj: self.camera.proj.to_cols_array_2d(),
+                view: view.to_cols_array_2d(),
+                proj: self.scene.camera.proj().to_cols_array_2d(),
             };
-            let buffer = self.uniform_buffer_allocator.allocate_sized().unwrap();
-            *buffer.write().unwrap() = uniform_data;
+            let buffer = uniform_allocator.allocate_sized()?;
+            *buffer.write()? = uniform_data;
+            buffer
+        };
+        let light_buffer = {
+            let lights = &self.scene.lights[..self.scene.lights.len().min(MAX_LIGHTS)];
+            let light_data = shader::mesh_fs::Lights {
+                lights: std::array::from_fn(|i| match lights.get(i) {
+                    Some(light) => shader_light(light, view, light_shadows[i]),
+                    None => shader::mesh_fs::Light {
+                        position: [0.0; 4],
+                        direction: [0.0; 4],
+                        color: [0.0; 4],
+                        attenuation: [0.0; 4],
+                        shadow_matrix: [[0.0; 4]; 4],
+                    },
+                }),
+                ambient: self.scene.ambient_light,
+                light_count: lights.len() as u32,
+            };
+            let buffer = uniform_allocator.allocate_sized()?;
+            *buffer.write()? = light_data;
             buffer
         };
         let layout = &self.pipeline.layout().set_layouts()[0];
         let descriptor_set = DescriptorSet::new(
             self.descriptor_set_allocator.clone(),
             layout.clone(),
-            [WriteDescriptorSet::buffer(0, uniform_buffer)],
+            [
+                WriteDescriptorSet::buffer(0, uniform_buffer),
+                WriteDescriptorSet::buffer(1, light_buffer),
+                WriteDescriptorSet::image_view_sampler(
+                    2,
+                    self.shadow_pass.view.clone(),
+                    self.shadow_pass.sampler.clone(),
+                ),
+            ],
             [],
-        )
-        .unwrap();
+        )?;
+        builder.bind_descriptor_sets(
+            PipelineBindPoint::Graphics,
+            self.pipeline.layout().clone(),
+            0,
+            descriptor_set,
+        )?;
+        Ok(())
+    }
+
+    fn draw_batch(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        batch: Batch,
+        instance_buffer: Subbuffer<[InstanceData]>,
+    ) -> Result<(), MagmaError> {
+        let Batch {
+            mesh,
+            material,
+            texture,
+            ..
+        } = batch;
+        let index_buffer_length = mesh.indices.len() as u32;
+        let instance_count = instance_buffer.len() as u32;
+        let [r, g, b] = material.base_color;
+        let material = shader::mesh_fs::Material {
+            base_color: [r, g, b, 1.0],
+            emissive: material.emissive,
+            shininess: material.shininess,
+        };
+        let texture_set = DescriptorSet::new(
+            self.descriptor_set_allocator.clone(),
+            self.pipeline.layout().set_layouts()[1].clone(),
+            [WriteDescriptorSet::image_view_sampler(
+                0,
+                texture,
+                self.sampler.clone(),
+            )],
+            [],
+        )?;
         builder
+            .push_constants(self.pipeline.layout().clone(), 0, material)?
             .bind_descriptor_sets(
                 PipelineBindPoint::Graphics,
                 self.pipeline.layout().clone(),
-                0,
-                descriptor_set,
-            )
-            .unwrap()
-            .bind_vertex_buffers(0, (pos_buffer, normals_buffer))
-            .unwrap()
-            .bind_index_buffer(index_buffer)
-            .unwrap();
-        unsafe { builder.draw_indexed(index_buffer_length, 1, 0, 0, 0) }.unwrap();
+                1,
+                texture_set,
+            )?
+            .bind_vertex_buffers(0, (mesh.positions, mesh.normals, mesh.uvs, instance_buffer))?
+            .bind_index_buffer(mesh.indices)?;
+        unsafe { builder.draw_indexed(index_buffer_length, instance_count, 0, 0, 0) }?;
+        Ok(())
     }
 
-    fn update_window_size(&mut self, window_size: PhysicalSize<u32>) {
+    fn rebuild_swapchain(
+        &mut self,
+        window_size: PhysicalSize<u32>,
+    ) -> Result<Arc<Swapchain>, MagmaError> {
         self.recreate_swapchain = false;
-        let (new_swapchain, new_images) = self
-            .swapchain
-            .recreate(SwapchainCreateInfo {
-                image_extent: window_size.into(),
-                ..self.swapchain.create_info()
-            })
-            .expect("engine: failed to recreate swapchain");
+        let RenderTarget::Window { swapchain, .. } = &mut self.target else {
+            unreachable!("engine: offscreen targets have no swapchain");
+        };
+        let (new_swapchain, new_images) =
+            target::recreate_swapchain(swapchain, window_size.into(), self.swapchain_config)?;
+        let format_changed = new_swapchain.image_format() != swapchain.image_format();
+        *swapchain = new_swapchain.clone();
         let aspect_ratio =
             new_swapchain.image_extent()[0] as f32 / new_swapchain.image_extent()[1] as f32;
-        self.camera.update_projection(aspect_ratio);
-        self.swapchain = new_swapchain;
+        self.scene.camera.update_projection(aspect_ratio);
+        // The viewport is dynamic, so only a new render pass requires a new
+        // pipeline.
+        if format_changed {
+            self.render_pass = create_render_pass(&self.device, new_swapchain.image_format())?;
+            self.pipeline = create_pipeline(
+                &self.device,
+                &self.render_pass,
+                self.vertex_shader.clone(),
+                self.fragment_shader.clone(),
+            )?;
+        }
         let new_framebuffers =
-            create_framebuffers(&self.memory_allocator, &new_images, &self.render_pass);
-        let new_pipeline = create_pipeline(
-            &self.device,
-            &self.render_pass,
-            self.vertex_shader.clone(),
-            self.fragment_shader.clone(),
-            window_size,
-        );
+            create_framebuffers(&self.memory_allocator, &new_images, &self.render_pass)?;
         self.framebuffers = new_framebuffers;
-        self.pipeline = new_pipeline;
+        Ok(new_swapchain)
+    }
+
+    // The aspect ratio of the scene camera is adjusted to the render target.
+    pub fn set_scene(&mut self, scene: Scene) {
+        let extent = self.target.extent();
+        self.scene = scene;
+        self.scene
+            .camera
+            .update_projection(extent[0] as f32 / extent[1] as f32);
+    }
+
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    // Changes to the camera projection are overwritten with the aspect ratio
+    // of the render target whenever it is resized.
+    pub fn scene_mut(&mut self) -> &mut Scene {
+        &mut self.scene
+    }
+
+    // Hands the scene back to the application and leaves an empty one.
+    pub fn take_scene(&mut self) -> Scene {
+        let scene = std::mem::take(&mut self.scene);
+        self.set_scene(Scene::new());
+        scene
     }
 
     pub fn recreate_swapchain(&mut self) {
         self.recreate_swapchain = true;
     }
+
+    pub fn swapchain_config(&self) -> SwapchainConfig {
+        self.swapchain_config
+    }
+
+    // The swapchain is recreated with the new format and present mode before
+    // the next frame. Has no effect on headless engines.
+    pub fn set_swapchain_config(&mut self, config: SwapchainConfig) {
+        if config != self.swapchain_config {
+            self.swapchain_config = config;
+            self.recreate_swapchain = true;
+        }
+    }
+
+    // The format and present mode actually in use, which may differ from the
+    // configuration if the surface does not support it.
+    pub fn swapchain_format(&self) -> (Format, Option<PresentMode>) {
+        match &self.target {
+            RenderTarget::Window { swapchain, .. } => {
+                (swapchain.image_format(), Some(swapchain.present_mode()))
+            }
+            RenderTarget::Offscreen { image } => (image.format(), None),
+        }
+    }
+}
+
+// The light in view space, in the layout of the fragment shader. The kind is
+// stored in the w component of the position and the cosines of the cone
+// angles of spot lights in those of the direction and color. The shadow map
+// layer is stored in that of the attenuation, -1 without shadows.
+fn shader_light(light: &Light, view: Mat4, shadow: Option<(u32, Mat4)>) -> shader::mesh_fs::Light {
+    let position = view.transform_point3(light.transform.translation);
+    let direction = view
+        .transform_vector3(light.direction())
+        .normalize_or_zero();
+    let (kind, cos_inner, cos_outer) = match light.kind {
+        LightKind::Directional => (0.0, 1.0, 0.0),
+        LightKind::Point => (1.0, 1.0, 0.0),
+        LightKind::Spot {
+            inner_angle,
+            outer_angle,
+        } => {
+            let cos_inner = inner_angle.cos();
+            // The edges must differ for a smooth transition.
+            (2.0, cos_inner, outer_angle.cos().min(cos_inner - 1e-4))
+        }
+    };
+    let [r, g, b] = light.color.map(|c| c * light.intensity);
+    let [c, l, q] = light.attenuation;
+    // Fragment positions are in view space, so the shadow matrix starts from
+    // there.
+    let (layer, shadow_matrix) = match shadow {
+        Some((layer, light_matrix)) => (layer as f32, light_matrix * view.inverse()),
+        None => (-1.0, Mat4::IDENTITY),
+    };
+    shader::mesh_fs::Light {
+        position: [position.x, position.y, position.z, kind],
+        direction: [direction.x, direction.y, direction.z, cos_inner],
+        color: [r, g, b, cos_outer],
+        attenuation: [c, l, q, layer],
+        shadow_matrix: shadow_matrix.to_cols_array_2d(),
+    }
+}
+
+// Number of whole steps in the accumulated time, which keeps the remainder.
+// Time beyond `MAX_FIXED_STEPS` steps is dropped.
+fn take_fixed_steps(accumulated_time: &mut Duration, timestep: Duration) -> u32 {
+    let steps = (accumulated_time.as_nanos() / timestep.as_nanos()) as u32;
+    if steps > MAX_FIXED_STEPS {
+        *accumulated_time = Duration::ZERO;
+        return MAX_FIXED_STEPS;
+    }
+    *accumulated_time -= timestep * steps;
+    steps
+}
+
+fn create_render_pass(device: &Arc<Device>, format: Format) -> Result<Arc<RenderPass>, MagmaError> {
+    let render_pass = vulkano::single_pass_renderpass!(
+        device.clone(),
+        attachments: {
+            color: {
+                format: format,
+                samples: 1,
+                load_op: Clear,
+                store_op: Store,
+            },
+            depth_stencil: {
+                format: Format::D16_UNORM,
+                samples: 1,
+                load_op: Clear,
+                store_op: DontCare,
+            },
+        },
+        pass: {
+            color: [color],
+            depth_stencil: {depth_stencil},
+        },
+    )?;
+    Ok(render_pass)
 }
 
 fn create_framebuffers(
     memory_allocator: &Arc<StandardMemoryAllocator>,
     images: &[Arc<Image>],
     render_pass: &Arc<RenderPass>,
-) -> Vec<Arc<Framebuffer>> {
-    let depth_buffer = ImageView::new_default(
-        Image::new(
-            memory_allocator.clone(),
-            ImageCreateInfo {
-                image_type: ImageType::Dim2d,
-                format: Format::D16_UNORM,
-                extent: images[0].extent(),
-                usage: ImageUsage::DEPTH_STENCIL_ATTACHMENT | ImageUsage::TRANSIENT_ATTACHMENT,
-                ..Default::default()
-            },
-            AllocationCreateInfo::default(),
-        )
-        .unwrap(),
-    )
-    .unwrap();
+) -> Result<Vec<Arc<Framebuffer>>, MagmaError> {
+    let depth_buffer = ImageView::new_default(Image::new(
+        memory_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::D16_UNORM,
+            extent: images[0].extent(),
+            usage: ImageUsage::DEPTH_STENCIL_ATTACHMENT | ImageUsage::TRANSIENT_ATTACHMENT,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    )?)?;
     images
         .iter()
         .map(|image| {
-            let view = ImageView::new_default(image.clone()).unwrap();
-            Framebuffer::new(
+            let view = ImageView::new_default(image.clone())?;
+            let framebuffer = Framebuffer::new(
                 render_pass.clone(),
                 FramebufferCreateInfo {
                     attachments: vec![view.clone(), depth_buffer.clone()],
                     ..Default::default()
                 },
-            )
-            .unwrap()
+            )?;
+            Ok(framebuffer)
         })
-        .collect::<Vec<_>>()
+        .collect()
+}
+
+// The `main` entry point of a shader module created by `vulkano_shaders`.
+fn load_shader(
+    name: &str,
+    module: Result<Arc<ShaderModule>, Validated<VulkanError>>,
+) -> Result<EntryPoint, MagmaError> {
+    module
+        .map_err(|e| MagmaError::ShaderLoad(format!("{name}: {e}")))?
+        .entry_point("main")
+        .ok_or_else(|| MagmaError::ShaderLoad(format!("{name}: no main entry point")))
+}
+
+// Restricts drawing to a rectangle of the framebuffer, in pixels.
+fn set_viewport(
+    builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    offset: [u32; 2],
+    extent: [u32; 2],
+) -> Result<(), MagmaError> {
+    let viewport = Viewport {
+        offset: [offset[0] as f32, offset[1] as f32],
+        extent: [extent[0] as f32, extent[1] as f32],
+        depth_range: 0.0..=1.0,
+    };
+    builder
+        .set_viewport(0, [viewport].into_iter().collect())?
+        .set_scissor(0, [Scissor { offset, extent }].into_iter().collect())?;
+    Ok(())
 }
 
 fn create_pipeline(
@@ -418,11 +966,28 @@ fn create_pipeline(
     render_pass: &Arc<RenderPass>,
     vs: EntryPoint,
     fs: EntryPoint,
-    window_size: PhysicalSize<u32>,
-) -> Arc<GraphicsPipeline> {
-    let vertex_input_state = [model::Position::per_vertex(), model::Normal::per_vertex()]
-        .definition(&vs)
-        .unwrap();
+) -> Result<Arc<GraphicsPipeline>, MagmaError> {
+    // Color attachments without sRGB format, e.g. of 10-bit swapchains, get
+    // the sRGB encoding from the fragment shader instead.
+    let format = render_pass.attachments()[0].format;
+    let encode_srgb = format.numeric_format_color() == Some(NumericFormat::UNORM);
+    let fs = fs
+        .module()
+        .base_module()
+        .specialize(
+            [(0, SpecializationConstant::Bool(encode_srgb))]
+                .into_iter()
+                .collect(),
+        )?
+        .entry_point("main")
+        .ok_or_else(|| MagmaError::ShaderLoad("mesh_fs: no main entry point".to_string()))?;
+    let vertex_input_state = [
+        mesh::Position::per_vertex(),
+        mesh::Normal::per_vertex(),
+        mesh::Uv::per_vertex(),
+        InstanceData::per_instance(),
+    ]
+    .definition(&vs)?;
     let stages = [
         PipelineShaderStageCreateInfo::new(vs),
         PipelineShaderStageCreateInfo::new(fs),
@@ -430,28 +995,20 @@ fn create_pipeline(
     let layout = PipelineLayout::new(
         device.clone(),
         PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages)
-            .into_pipeline_layout_create_info(device.clone())
-            .unwrap(),
-    )
-    .unwrap();
-    let subpass = Subpass::from(render_pass.clone(), 0).unwrap();
-    GraphicsPipeline::new(
+            .into_pipeline_layout_create_info(device.clone())?,
+    )?;
+    // Render passes of the engine have a single subpass.
+    let subpass =
+        Subpass::from(render_pass.clone(), 0).expect("engine: render pass has no subpass");
+    let pipeline = GraphicsPipeline::new(
         device.clone(),
         None,
         GraphicsPipelineCreateInfo {
             stages: stages.into_iter().collect(),
             vertex_input_state: Some(vertex_input_state),
             input_assembly_state: Some(InputAssemblyState::default()),
-            viewport_state: Some(ViewportState {
-                viewports: [Viewport {
-                    offset: [0.0, 0.0],
-                    extent: window_size.into(),
-                    depth_range: 0.0..=1.0,
-                }]
-                .into_iter()
-                .collect(),
-                ..Default::default()
-            }),
+            // One viewport and scissor, both set while recording.
+            viewport_state: Some(ViewportState::default()),
             rasterization_state: Some(RasterizationState::default()),
             depth_stencil_state: Some(DepthStencilState {
                 depth: Some(DepthState::simple()),
@@ -462,9 +1019,33 @@ fn create_pipeline(
                 subpass.num_color_attachments(),
                 ColorBlendAttachmentState::default(),
             )),
+            dynamic_state: [DynamicState::Viewport, DynamicState::Scissor]
+                .into_iter()
+                .collect(),
             subpass: Some((subpass).into()),
             ..GraphicsPipelineCreateInfo::layout(layout)
         },
-    )
-    .unwrap()
+    )?;
+    Ok(pipeline)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_steps() {
+        let timestep = Duration::from_millis(10);
+        let mut accumulated_time = Duration::from_millis(25);
+        assert_eq!(take_fixed_steps(&mut accumulated_time, timestep), 2);
+        assert_eq!(accumulated_time, Duration::from_millis(5));
+        accumulated_time += Duration::from_millis(4);
+        assert_eq!(take_fixed_steps(&mut accumulated_time, timestep), 0);
+        accumulated_time += Duration::from_secs(1);
+        assert_eq!(
+            take_fixed_steps(&mut accumulated_time, timestep),
+            MAX_FIXED_STEPS
+        );
+        assert_eq!(accumulated_time, Duration::ZERO);
+    }
 }
@@ -1,144 +1,55 @@
 use glam::{Mat4, Vec3};
-use std::f32::consts::TAU;
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
 use std::sync::Arc;
-use vulkano::buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer};
-use vulkano::memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator};
+use vulkano::buffer::BufferContents;
 use vulkano::pipeline::graphics::vertex_input::Vertex;
 
-#[derive(BufferContents, Vertex, Clone)]
-#[repr(C)]
-pub struct Position {
-    #[format(R32G32B32_SFLOAT)]
-    position: [f32; 3],
-}
+use crate::engine::material::Material;
+use crate::engine::mesh::{Mesh, MeshSource, Normal, Position, Uv};
+use crate::engine::texture::Texture;
+use crate::engine::transform::Transform;
 
+// Per instance vertex data of instanced draws.
 #[derive(BufferContents, Vertex, Clone)]
 #[repr(C)]
-pub struct Normal {
-    #[format(R32G32B32_SFLOAT)]
-    normal: [f32; 3],
+pub struct InstanceData {
+    #[format(R32G32B32A32_SFLOAT)]
+    pub world: [[f32; 4]; 4],
 }
 
+pub const DEFAULT_COLOR: [f32; 3] = [1.0, 0.0, 0.0];
+
+// An instance of a mesh placed in the scene.
 pub struct Model {
-    positions: Vec<Position>,
-    normals: Vec<Normal>,
-    indices: Vec<u16>,
-    translation: Vec3,
-    rotation_x: f32,
-    rotation_y: f32,
-    rotation_z: f32,
+    mesh: Arc<Mesh>,
+    pub material: Material,
+    // Multiplied with the base color of the material.
+    pub texture: Option<Arc<Texture>>,
+    // Relative to the parent, see `Scene::world_matrix`.
+    pub transform: Transform,
 }
 
 impl Model {
-    pub fn new(positions: Vec<Position>, normals: Vec<Normal>, indeces: Vec<u16>) -> Self {
+    pub fn new(mesh: Arc<Mesh>) -> Self {
         Model {
-            positions: positions,
-            normals: normals,
-            indices: indeces,
-            translation: Vec3 {
-                x: 0.0,
-                y: 0.0,
-                z: 0.0,
-            },
-            rotation_x: 0.0,
-            rotation_y: 0.0,
-            rotation_z: 0.0,
+            mesh,
+            material: Material::DEFAULT,
+            texture: None,
+            transform: Transform::IDENTITY,
         }
     }
 
-    pub fn create_vertex_buffer(
-        &self,
-        memory_allocator: &Arc<StandardMemoryAllocator>,
-    ) -> Subbuffer<[Position]> {
-        Buffer::from_iter(
-            memory_allocator.clone(),
-            BufferCreateInfo {
-                usage: BufferUsage::VERTEX_BUFFER,
-                ..Default::default()
-            },
-            AllocationCreateInfo {
-                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
-                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
-                ..Default::default()
-            },
-            self.positions.clone(),
-        )
-        .unwrap()
-    }
-
-    pub fn create_normals_buffer(
-        &self,
-        memory_allocator: &Arc<StandardMemoryAllocator>,
-    ) -> Subbuffer<[Normal]> {
-        Buffer::from_iter(
-            memory_allocator.clone(),
-            BufferCreateInfo {
-                usage: BufferUsage::VERTEX_BUFFER,
-                ..Default::default()
-            },
-            AllocationCreateInfo {
-                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
-                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
-                ..Default::default()
-            },
-            self.normals.clone(),
-        )
-        .unwrap()
-    }
-
-    pub fn create_index_buffer(
-        &self,
-        memory_allocator: &Arc<StandardMemoryAllocator>,
-    ) -> Subbuffer<[u16]> {
-        Buffer::from_iter(
-            memory_allocator.clone(),
-            BufferCreateInfo {
-                usage: BufferUsage::INDEX_BUFFER,
-                ..Default::default()
-            },
-            AllocationCreateInfo {
-                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
-                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
-                ..Default::default()
-            },
-            self.indices.clone(),
-        )
-        .unwrap()
+    pub fn mesh(&self) -> &Arc<Mesh> {
+        &self.mesh
     }
 
     pub fn get_model_matrix(&self) -> Mat4 {
-        let mut model_matrix = Mat4::IDENTITY;
-        if self.translation.length() > 0.0 {
-            let m_t = Mat4::from_translation(self.translation);
-            model_matrix *= m_t
-        }
-        if self.rotation_x != 0.0 {
-            let m_rx = Mat4::from_rotation_x(self.rotation_x);
-            model_matrix *= m_rx
-        }
-        if self.rotation_y != 0.0 {
-            let m_ry = Mat4::from_rotation_y(self.rotation_y);
-            model_matrix *= m_ry
-        }
-        if self.rotation_z != 0.0 {
-            let m_rz = Mat4::from_rotation_z(self.rotation_z);
-            model_matrix *= m_rz
-        }
-        return model_matrix;
-    }
-
-    pub fn rotate(&mut self, x: f32, y: f32, z: f32) {
-        self.rotation_x = (self.rotation_x + x) % TAU;
-        self.rotation_y = (self.rotation_y + y) % TAU;
-        self.rotation_z = (self.rotation_z + z) % TAU;
-    }
-
-    pub fn translate(&mut self, vt: Vec3) {
-        self.translation += vt;
+        self.transform.matrix()
     }
 }
 
-pub fn get_cube() -> Model {
+pub fn get_cube_mesh() -> Arc<Mesh> {
     let positions = vec![
         Position {
             position: [-0.5, -0.5, 0.5],
@@ -291,5 +202,311 @@ pub fn get_cube() -> Model {
         0, 1, 2, 1, 2, 3, 4, 5, 6, 5, 6, 7, 8, 9, 10, 9, 10, 11, 12, 13, 14, 13, 14, 15, 16, 17,
         18, 17, 18, 19, 20, 21, 22, 21, 22, 23,
     ];
-    Model::new(positions, normals, indices)
+    // Every face shows the whole texture.
+    let uvs = [[0.0, 1.0], [1.0, 1.0], [0.0, 0.0], [1.0, 0.0]]
+        .repeat(6)
+        .into_iter()
+        .map(|uv| Uv { uv })
+        .collect();
+    Arc::new(
+        Mesh::new(positions, normals, indices)
+            .with_uvs(uvs)
+            .with_source(MeshSource::Cube),
+    )
+}
+
+// The generators below create meshes centered at the origin that fit into a
+// unit cube like `get_cube_mesh`. Subdivision counts are clamped to the
+// smallest values that still produce a closed shape. Curved surfaces are
+// unwrapped around the y axis, with u growing with the azimuth and v from
+// top to bottom.
+
+// Sphere of latitude rings and longitude segments with poles on the y axis.
+pub fn get_uv_sphere_mesh(segments: u32, rings: u32) -> Arc<Mesh> {
+    let (segments, rings) = (segments.max(3), rings.max(2));
+    let mut builder = MeshBuilder::default();
+    for ring in 0..=rings {
+        let phi = PI * ring as f32 / rings as f32;
+        for segment in 0..=segments {
+            let normal = spherical(phi, TAU * segment as f32 / segments as f32);
+            let uv = [segment as f32 / segments as f32, ring as f32 / rings as f32];
+            builder.vertex(0.5 * normal, normal, uv);
+        }
+    }
+    builder.grid(0, rings + 1, segments + 1);
+    builder.build(MeshSource::UvSphere { segments, rings })
+}
+
+// Sphere made of an icosahedron whose faces are split into four triangles
+// per subdivision, giving evenly sized faces.
+pub fn get_icosphere_mesh(subdivisions: u32) -> Arc<Mesh> {
+    let t = (1.0 + 5f32.sqrt()) / 2.0;
+    let mut corners = vec![
+        Vec3::new(-1.0, t, 0.0),
+        Vec3::new(1.0, t, 0.0),
+        Vec3::new(-1.0, -t, 0.0),
+        Vec3::new(1.0, -t, 0.0),
+        Vec3::new(0.0, -1.0, t),
+        Vec3::new(0.0, 1.0, t),
+        Vec3::new(0.0, -1.0, -t),
+        Vec3::new(0.0, 1.0, -t),
+        Vec3::new(t, 0.0, -1.0),
+        Vec3::new(t, 0.0, 1.0),
+        Vec3::new(-t, 0.0, -1.0),
+        Vec3::new(-t, 0.0, 1.0),
+    ];
+    let mut faces = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+    for _ in 0..subdivisions {
+        // Edges are shared by two faces, so their midpoints are cached.
+        let mut midpoints = HashMap::new();
+        let mut midpoint = |a: u32, b: u32| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                corners.push((corners[a as usize] + corners[b as usize]) / 2.0);
+                corners.len() as u32 - 1
+            })
+        };
+        faces = faces
+            .into_iter()
+            .flat_map(|[a, b, c]| {
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+    let mut builder = MeshBuilder::default();
+    // Vertices are shared across the seam of the texture, so triangles
+    // crossing it show the whole texture squeezed.
+    for corner in corners {
+        let normal = corner.normalize();
+        let u = (normal.z.atan2(normal.x) / TAU).rem_euclid(1.0);
+        builder.vertex(0.5 * normal, normal, [u, normal.y.acos() / PI]);
+    }
+    builder.indices = faces.concat();
+    builder.build(MeshSource::Icosphere { subdivisions })
+}
+
+// Square in the xz plane facing up, split into a grid of equally sized cells.
+pub fn get_plane_mesh(subdivisions: u32) -> Arc<Mesh> {
+    let cells = subdivisions.max(1);
+    let mut builder = MeshBuilder::default();
+    for x in 0..=cells {
+        for z in 0..=cells {
+            let uv = Vec3::new(x as f32, 0.0, z as f32) / cells as f32;
+            builder.vertex(uv - Vec3::new(0.5, 0.0, 0.5), Vec3::Y, [uv.x, uv.z]);
+        }
+    }
+    builder.grid(0, cells + 1, cells + 1);
+    builder.build(MeshSource::Plane {
+        subdivisions: cells,
+    })
+}
+
+pub fn get_cylinder_mesh(segments: u32) -> Arc<Mesh> {
+    let segments = segments.max(3);
+    let mut builder = MeshBuilder::default();
+    for segment in 0..=segments {
+        let u = segment as f32 / segments as f32;
+        let normal = spherical(FRAC_PI_2, TAU * u);
+        builder.vertex(0.5 * normal - 0.5 * Vec3::Y, normal, [u, 1.0]);
+        builder.vertex(0.5 * normal + 0.5 * Vec3::Y, normal, [u, 0.0]);
+    }
+    builder.grid(0, segments + 1, 2);
+    builder.cap(-0.5, 0.5, segments);
+    builder.cap(0.5, 0.5, segments);
+    builder.build(MeshSource::Cylinder { segments })
+}
+
+// Cone with its apex pointing up.
+pub fn get_cone_mesh(segments: u32) -> Arc<Mesh> {
+    let segments = segments.max(3);
+    let mut builder = MeshBuilder::default();
+    for segment in 0..=segments {
+        let u = segment as f32 / segments as f32;
+        let direction = spherical(FRAC_PI_2, TAU * u);
+        // Perpendicular to the slope of a cone that is twice as high as wide.
+        let normal = (direction + 0.5 * Vec3::Y).normalize();
+        builder.vertex(0.5 * direction - 0.5 * Vec3::Y, normal, [u, 1.0]);
+        builder.vertex(0.5 * Vec3::Y, normal, [u, 0.0]);
+    }
+    builder.grid(0, segments + 1, 2);
+    builder.cap(-0.5, 0.5, segments);
+    builder.build(MeshSource::Cone { segments })
+}
+
+// Torus around the y axis. `ring_segments` subdivide the ring, `tube_segments`
+// the cross section of the tube.
+pub fn get_torus_mesh(ring_segments: u32, tube_segments: u32) -> Arc<Mesh> {
+    const RING_RADIUS: f32 = 0.35;
+    const TUBE_RADIUS: f32 = 0.15;
+    let (ring_segments, tube_segments) = (ring_segments.max(3), tube_segments.max(3));
+    let mut builder = MeshBuilder::default();
+    for ring in 0..=ring_segments {
+        let direction = spherical(FRAC_PI_2, TAU * ring as f32 / ring_segments as f32);
+        for tube in 0..=tube_segments {
+            let angle = TAU * tube as f32 / tube_segments as f32;
+            let normal = angle.cos() * direction + angle.sin() * Vec3::Y;
+            let uv = [
+                ring as f32 / ring_segments as f32,
+                tube as f32 / tube_segments as f32,
+            ];
+            builder.vertex(RING_RADIUS * direction + TUBE_RADIUS * normal, normal, uv);
+        }
+    }
+    builder.grid(0, ring_segments + 1, tube_segments + 1);
+    builder.build(MeshSource::Torus {
+        ring_segments,
+        tube_segments,
+    })
+}
+
+// Cylinder along the y axis with hemispheres as caps. `rings` subdivide each
+// hemisphere.
+pub fn get_capsule_mesh(segments: u32, rings: u32) -> Arc<Mesh> {
+    const RADIUS: f32 = 0.25;
+    let (segments, rings) = (segments.max(3), rings.max(1));
+    let mut builder = MeshBuilder::default();
+    let rows = 2 * (rings + 1);
+    for (half, (offset, first_phi)) in [(0.25, 0.0), (-0.25, FRAC_PI_2)].into_iter().enumerate() {
+        for ring in 0..=rings {
+            let phi = first_phi + FRAC_PI_2 * ring as f32 / rings as f32;
+            let v = (half as u32 * (rings + 1) + ring) as f32 / (rows - 1) as f32;
+            for segment in 0..=segments {
+                let u = segment as f32 / segments as f32;
+                let normal = spherical(phi, TAU * u);
+                builder.vertex(RADIUS * normal + offset * Vec3::Y, normal, [u, v]);
+            }
+        }
+    }
+    builder.grid(0, rows, segments + 1);
+    builder.build(MeshSource::Capsule { segments, rings })
+}
+
+// Unit vector with polar angle `phi` measured from the y axis and azimuth
+// `theta` measured from the x axis towards the z axis.
+fn spherical(phi: f32, theta: f32) -> Vec3 {
+    Vec3::new(phi.sin() * theta.cos(), phi.cos(), phi.sin() * theta.sin())
+}
+
+#[derive(Default)]
+struct MeshBuilder {
+    positions: Vec<Position>,
+    normals: Vec<Normal>,
+    uvs: Vec<Uv>,
+    indices: Vec<u32>,
+}
+
+impl MeshBuilder {
+    fn vertex(&mut self, position: Vec3, normal: Vec3, uv: [f32; 2]) -> u32 {
+        self.positions.push(Position {
+            position: position.into(),
+        });
+        self.normals.push(Normal {
+            normal: normal.into(),
+        });
+        self.uvs.push(Uv { uv });
+        self.positions.len() as u32 - 1
+    }
+
+    // Connects a block of `rows * columns` vertices, stored row by row, with
+    // two triangles per cell.
+    fn grid(&mut self, first: u32, rows: u32, columns: u32) {
+        for row in 0..rows - 1 {
+            for column in 0..columns - 1 {
+                let a = first + row * columns + column;
+                let b = a + columns;
+                self.indices.extend([a, a + 1, b + 1, a, b + 1, b]);
+            }
+        }
+    }
+
+    // Flat disk at height `y` around the y axis, facing away from the origin.
+    // The texture is projected onto it from above.
+    fn cap(&mut self, y: f32, radius: f32, segments: u32) {
+        let normal = Vec3::Y * y.signum();
+        let center = self.vertex(Vec3::new(0.0, y, 0.0), normal, [0.5, 0.5]);
+        for segment in 0..=segments {
+            let direction = spherical(FRAC_PI_2, TAU * segment as f32 / segments as f32);
+            let uv = [0.5 + 0.5 * direction.x, 0.5 + 0.5 * direction.z];
+            self.vertex(radius * direction + Vec3::new(0.0, y, 0.0), normal, uv);
+        }
+        for segment in 0..segments {
+            let (a, b) = (center + 1 + segment, center + 2 + segment);
+            if y > 0.0 {
+                self.indices.extend([center, b, a]);
+            } else {
+                self.indices.extend([center, a, b]);
+            }
+        }
+    }
+
+    fn build(self, source: MeshSource) -> Arc<Mesh> {
+        Arc::new(
+            Mesh::new(self.positions, self.normals, self.indices)
+                .with_uvs(self.uvs)
+                .with_source(source),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn primitives_are_well_formed() {
+        let meshes = [
+            get_uv_sphere_mesh(16, 8),
+            get_icosphere_mesh(2),
+            get_plane_mesh(4),
+            get_cylinder_mesh(16),
+            get_cone_mesh(16),
+            get_torus_mesh(16, 8),
+            get_capsule_mesh(16, 4),
+        ];
+        for mesh in meshes {
+            assert_eq!(mesh.positions().len(), mesh.normals().len());
+            for uv in mesh.uvs() {
+                assert!(uv.uv.iter().all(|c| (0.0..=1.0).contains(c)));
+            }
+            assert_eq!(mesh.indices().len() % 3, 0);
+            for normal in mesh.normals() {
+                assert!((Vec3::from(normal.normal).length() - 1.0).abs() < 1e-5);
+            }
+            for triangle in mesh.indices().chunks_exact(3) {
+                let vertex = |i: usize| {
+                    let index = triangle[i] as usize;
+                    (
+                        Vec3::from(mesh.positions()[index].position),
+                        Vec3::from(mesh.normals()[index].normal),
+                    )
+                };
+                let [(a, na), (b, nb), (c, nc)] = [vertex(0), vertex(1), vertex(2)];
+                assert!(a.abs().max_element() <= 0.5 + 1e-5);
+                // Triangles wind counter clockwise when seen from outside.
+                let face_normal = (b - a).cross(c - a);
+                assert!(face_normal.dot(na + nb + nc) >= -1e-6);
+            }
+        }
+    }
 }
@@ -0,0 +1,338 @@
+use glam::{Mat3, Mat4, Vec3};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::engine::mesh::{self, Mesh, MeshSource, Normal, Position, Uv};
+use crate::engine::model::Model;
+use crate::engine::scene::Scene;
+
+// An object (`o`) or group (`g`) of a Wavefront OBJ file. Objects using
+// several materials are split into one model per material.
+pub struct ObjObject {
+    pub name: String,
+    pub model: Model,
+}
+
+// Loads all objects of an OBJ file into a scene. Diffuse colors (`Kd`) of
+// materials referenced through `mtllib` become the colors of the models.
+pub fn load_scene(path: impl AsRef<Path>) -> io::Result<Scene> {
+    let mut scene = Scene::new();
+    for object in load(path)? {
+        scene.insert(object.name, object.model);
+    }
+    Ok(scene)
+}
+
+pub fn load(path: impl AsRef<Path>) -> io::Result<Vec<ObjObject>> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)?;
+    let mut materials = HashMap::new();
+    for line in source.lines() {
+        if let Some(library) = line.trim().strip_prefix("mtllib ") {
+            let library_path = path.with_file_name(library.trim());
+            materials.extend(parse_mtl(&fs::read_to_string(library_path)?));
+        }
+    }
+    parse(&source, &materials, Some(path))
+}
+
+// Diffuse colors of the materials of an MTL file.
+pub fn parse_mtl(source: &str) -> HashMap<String, [f32; 3]> {
+    let mut colors = HashMap::new();
+    let mut material = None;
+    for line in source.lines() {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("newmtl") => material = tokens.next().map(str::to_string),
+            Some("Kd") => {
+                if let (Some(material), Some(color)) = (&material, parse_vec3(tokens)) {
+                    colors.insert(material.clone(), color);
+                }
+            }
+            _ => {}
+        }
+    }
+    colors
+}
+
+// Indices of the position, texture coordinate and normal of a face vertex.
+type VertexKey = (usize, Option<usize>, Option<usize>);
+
+// Builds the geometry of one object. OBJ indexes positions, texture
+// coordinates and normals separately, so every distinct combination becomes
+// one vertex.
+#[derive(Default)]
+struct ObjectBuilder {
+    name: String,
+    material: Option<String>,
+    vertices: HashMap<VertexKey, u32>,
+    positions: Vec<Position>,
+    uvs: Vec<Option<Uv>>,
+    normals: Vec<Option<Normal>>,
+    indices: Vec<u32>,
+}
+
+impl ObjectBuilder {
+    fn new(name: String, material: Option<String>) -> Self {
+        ObjectBuilder {
+            name,
+            material,
+            ..Default::default()
+        }
+    }
+
+    fn vertex(
+        &mut self,
+        positions: &[[f32; 3]],
+        uvs: &[[f32; 2]],
+        normals: &[[f32; 3]],
+        key: VertexKey,
+    ) -> u32 {
+        *self.vertices.entry(key).or_insert_with(|| {
+            self.positions.push(Position {
+                position: positions[key.0],
+            });
+            self.uvs.push(key.1.map(|t| Uv { uv: uvs[t] }));
+            self.normals
+                .push(key.2.map(|n| Normal { normal: normals[n] }));
+            self.positions.len() as u32 - 1
+        })
+    }
+
+    fn build(
+        self,
+        materials: &HashMap<String, [f32; 3]>,
+        source: Option<MeshSource>,
+    ) -> Option<ObjObject> {
+        if self.indices.is_empty() {
+            return None;
+        }
+        let normals = if self.normals.iter().all(Option::is_some) {
+            self.normals.into_iter().flatten().collect()
+        } else {
+            mesh::compute_normals(&self.positions, &self.indices)
+        };
+        let mut mesh = Mesh::new(self.positions, normals, self.indices);
+        if self.uvs.iter().all(Option::is_some) {
+            mesh = mesh.with_uvs(self.uvs.into_iter().flatten().collect());
+        }
+        if let Some(source) = source {
+            mesh = mesh.with_source(source);
+        }
+        let mut model = Model::new(Arc::new(mesh));
+        if let Some(color) = self.material.and_then(|m| materials.get(&m)) {
+            model.material.base_color = *color;
+        }
+        Some(ObjObject {
+            name: self.name,
+            model,
+        })
+    }
+}
+
+// `path` is the file the source was read from, which is recorded as source
+// of the meshes.
+pub fn parse(
+    source: &str,
+    materials: &HashMap<String, [f32; 3]>,
+    path: Option<&Path>,
+) -> io::Result<Vec<ObjObject>> {
+    let mesh_source = |object: usize| {
+        path.map(|path| MeshSource::Obj {
+            path: path.to_path_buf(),
+            object,
+        })
+    };
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+    let mut objects = Vec::new();
+    let mut current = ObjectBuilder::new("default".to_string(), None);
+    for (number, line) in source.lines().enumerate() {
+        let error = |message: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("obj: line {}: {message}", number + 1),
+            )
+        };
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => positions.push(parse_vec3(tokens).ok_or_else(|| error("invalid vertex"))?),
+            Some("vt") => uvs.push(parse_uv(tokens).ok_or_else(|| error("invalid uv"))?),
+            Some("vn") => normals.push(parse_vec3(tokens).ok_or_else(|| error("invalid normal"))?),
+            Some(keyword @ ("o" | "g" | "usemtl")) => {
+                let value = tokens.collect::<Vec<_>>().join(" ");
+                let (name, material) = match keyword {
+                    "usemtl" => (current.name.clone(), Some(value)),
+                    _ => (value, current.material.clone()),
+                };
+                let previous = std::mem::replace(&mut current, ObjectBuilder::new(name, material));
+                objects.extend(previous.build(materials, mesh_source(objects.len())));
+            }
+            Some("f") => {
+                let face = tokens
+                    .map(|t| parse_face_vertex(t, [positions.len(), uvs.len(), normals.len()]))
+                    .collect::<Option<Vec<_>>>()
+                    .filter(|face| face.len() >= 3)
+                    .ok_or_else(|| error("invalid face"))?;
+                let face = face
+                    .into_iter()
+                    .map(|key| current.vertex(&positions, &uvs, &normals, key))
+                    .collect::<Vec<_>>();
+                // Polygons are triangulated as a fan.
+                for i in 1..face.len() - 1 {
+                    current.indices.extend([face[0], face[i], face[i + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+    objects.extend(current.build(materials, mesh_source(objects.len())));
+    Ok(objects)
+}
+
+fn parse_vec3<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Option<[f32; 3]> {
+    let mut vec = [0.0; 3];
+    for component in &mut vec {
+        *component = tokens.next()?.parse().ok()?;
+    }
+    Some(vec)
+}
+
+// OBJ puts the origin of texture coordinates at the bottom left, so v is
+// flipped. A third coordinate is ignored.
+fn parse_uv<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Option<[f32; 2]> {
+    let u: f32 = tokens.next()?.parse().ok()?;
+    let v: f32 = tokens.next().map_or(Some(0.0), |v| v.parse().ok())?;
+    Some([u, 1.0 - v])
+}
+
+// Parses `v`, `v/vt`, `v//vn` or `v/vt/vn` into zero based indices, given
+// the number of positions, texture coordinates and normals read so far.
+// Negative indices are relative to the end of the lists.
+fn parse_face_vertex(token: &str, counts: [usize; 3]) -> Option<VertexKey> {
+    let resolve = |index: &str, count: usize| -> Option<usize> {
+        let index = index.parse::<i64>().ok()?;
+        let index = if index < 0 {
+            count as i64 + index
+        } else {
+            index - 1
+        };
+        (0..count as i64).contains(&index).then_some(index as usize)
+    };
+    let mut parts = token.split('/');
+    let position = resolve(parts.next()?, counts[0])?;
+    let mut optional = |count: usize| match parts.next() {
+        Some(index) if !index.is_empty() => resolve(index, count).map(Some),
+        _ => Some(None),
+    };
+    let uv = optional(counts[1])?;
+    let normal = optional(counts[2])?;
+    Some((position, uv, normal))
+}
+
+// Writes all models of the scene into one OBJ file, one object per model.
+// Geometry is written in world space, i.e. with the model transforms applied.
+pub fn save(scene: &Scene, path: impl AsRef<Path>) -> io::Result<()> {
+    let world_matrices = scene.world_matrices();
+    fs::write(
+        path,
+        write(scene.iter().map(|(handle, model)| {
+            (
+                scene.name(handle).unwrap(),
+                world_matrices[&handle],
+                model.mesh().as_ref(),
+            )
+        })),
+    )
+}
+
+// Writes each mesh transformed by the given world matrix.
+pub fn write<'a>(meshes: impl IntoIterator<Item = (&'a str, Mat4, &'a Mesh)>) -> String {
+    let mut obj = String::new();
+    let mut offset = 1;
+    for (name, matrix, mesh) in meshes {
+        let normal_matrix = Mat3::from_mat4(matrix).inverse().transpose();
+        writeln!(obj, "o {name}").unwrap();
+        for position in mesh.positions() {
+            let [x, y, z] = matrix
+                .transform_point3(Vec3::from(position.position))
+                .to_array();
+            writeln!(obj, "v {x} {y} {z}").unwrap();
+        }
+        for normal in mesh.normals() {
+            let [x, y, z] = (normal_matrix * Vec3::from(normal.normal))
+                .normalize_or_zero()
+                .to_array();
+            writeln!(obj, "vn {x} {y} {z}").unwrap();
+        }
+        for uv in mesh.uvs() {
+            let [u, v] = uv.uv;
+            writeln!(obj, "vt {u} {}", 1.0 - v).unwrap();
+        }
+        for triangle in mesh.indices().chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize + offset);
+            writeln!(obj, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}").unwrap();
+        }
+        offset += mesh.positions().len();
+    }
+    obj
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUAD: &str = "
+mtllib quad.mtl
+o quad
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+usemtl green
+f 1/1/1 2/2/1 3/3/1 -1/-1/-1
+";
+
+    #[test]
+    fn parse_quad() {
+        let materials = parse_mtl("newmtl green\nKd 0.0 1.0 0.0\n");
+        let objects = parse(QUAD, &materials, None).unwrap();
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].name, "quad");
+        assert_eq!(objects[0].model.material.base_color, [0.0, 1.0, 0.0]);
+        let mesh = objects[0].model.mesh();
+        assert_eq!(mesh.positions().len(), 4);
+        assert_eq!(mesh.indices(), &[0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.uvs()[0].uv, [0.0, 1.0]);
+    }
+
+    #[test]
+    fn write_and_parse() {
+        let objects = parse(QUAD, &HashMap::new(), None).unwrap();
+        let obj = write([("quad", Mat4::IDENTITY, objects[0].model.mesh().as_ref())]);
+        let objects = parse(&obj, &HashMap::new(), None).unwrap();
+        let mesh = objects[0].model.mesh();
+        assert_eq!(mesh.positions().len(), 4);
+        assert_eq!(mesh.indices().len(), 6);
+        assert_eq!(mesh.normals()[0].normal, [0.0, 0.0, 1.0]);
+        assert_eq!(mesh.uvs()[2].uv, [1.0, 0.0]);
+    }
+
+    #[test]
+    fn reject_invalid_face() {
+        let error = parse("v 0 0 0\nf 1 2 3\n", &HashMap::new(), None)
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "obj: line 2: invalid face");
+    }
+}
@@ -1,15 +1,386 @@
+use crate::engine::camera::Camera;
+use crate::engine::light::{DEFAULT_AMBIENT_LIGHT, Light};
+use crate::engine::model;
 use crate::engine::model::Model;
+use crate::engine::scene_file::{self, SceneFileError};
 
+use glam::{Mat4, Vec3};
 use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
 
+// Refers to a model of a scene. Handles of removed models stay invalid even
+// if their slot is reused, since the generation of the slot changes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ModelHandle {
+    index: u32,
+    generation: u32,
+}
+
+struct Slot {
+    generation: u32,
+    node: Option<Node>,
+}
+
+struct Node {
+    name: String,
+    model: Model,
+    parent: Option<ModelHandle>,
+}
+
+// Why `Scene::set_parent` left the hierarchy unchanged.
+#[derive(Debug, PartialEq)]
+pub enum HierarchyError {
+    // The handle refers to a removed model.
+    StaleHandle(ModelHandle),
+    // The parent is the child itself or one of its descendants.
+    Cycle(ModelHandle),
+}
+
+impl fmt::Display for HierarchyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HierarchyError::StaleHandle(_) => write!(f, "scene: model was removed"),
+            HierarchyError::Cycle(_) => {
+                write!(f, "scene: a model can't become a descendant of itself")
+            }
+        }
+    }
+}
+
+impl std::error::Error for HierarchyError {}
+
+// Models form a hierarchy in which the transform of a model is relative to
+// its parent. Models without parent, or whose parent has been removed, are
+// placed in world space. Models are iterated in the order of their slots,
+// so scenes built the same way are always drawn in the same order. Scenes
+// without lights are lit by a headlight at the camera.
 pub struct Scene {
-    pub models: HashMap<String, Model>,
+    pub camera: Camera,
+    pub lights: Vec<Light>,
+    pub ambient_light: [f32; 3],
+    slots: Vec<Slot>,
+    free_slots: Vec<u32>,
+    names: HashMap<String, ModelHandle>,
 }
 
 impl Scene {
     pub fn new() -> Self {
         Scene {
-            models: HashMap::new(),
+            camera: Camera::new(1.0),
+            lights: Vec::new(),
+            ambient_light: DEFAULT_AMBIENT_LIGHT,
+            slots: Vec::new(),
+            free_slots: Vec::new(),
+            names: HashMap::new(),
+        }
+    }
+
+    // Reads a scene file, see `scene_file` for the format.
+    pub fn load(path: impl AsRef<Path>) -> Result<Scene, SceneFileError> {
+        scene_file::load(path)
+    }
+
+    // Writes a scene file. All meshes must have a source, i.e. come from a
+    // generator or a glTF or OBJ file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SceneFileError> {
+        scene_file::save(self, path)
+    }
+
+    // Adds a model without parent. If the name is taken, the first free
+    // `#<number>` suffix is appended to it.
+    pub fn insert(&mut self, name: impl Into<String>, model: Model) -> ModelHandle {
+        let name = self.unique_name(name.into());
+        let node = Node {
+            name: name.clone(),
+            model,
+            parent: None,
+        };
+        let handle = match self.free_slots.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.node = Some(node);
+                ModelHandle {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    node: Some(node),
+                });
+                ModelHandle {
+                    index: self.slots.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        };
+        self.names.insert(name, handle);
+        handle
+    }
+
+    // Children of the removed model are placed in world space.
+    pub fn remove(&mut self, handle: ModelHandle) -> Option<Model> {
+        self.node(handle)?;
+        let slot = &mut self.slots[handle.index as usize];
+        let node = slot.node.take().unwrap();
+        slot.generation += 1;
+        self.free_slots.push(handle.index);
+        self.names.remove(&node.name);
+        Some(node.model)
+    }
+
+    fn node(&self, handle: ModelHandle) -> Option<&Node> {
+        let slot = self.slots.get(handle.index as usize)?;
+        slot.node
+            .as_ref()
+            .filter(|_| slot.generation == handle.generation)
+    }
+
+    fn node_mut(&mut self, handle: ModelHandle) -> Option<&mut Node> {
+        let slot = self.slots.get_mut(handle.index as usize)?;
+        slot.node
+            .as_mut()
+            .filter(|_| slot.generation == handle.generation)
+    }
+
+    pub fn get(&self, handle: ModelHandle) -> Option<&Model> {
+        self.node(handle).map(|node| &node.model)
+    }
+
+    pub fn get_mut(&mut self, handle: ModelHandle) -> Option<&mut Model> {
+        self.node_mut(handle).map(|node| &mut node.model)
+    }
+
+    pub fn find(&self, name: &str) -> Option<ModelHandle> {
+        self.names.get(name).copied()
+    }
+
+    pub fn name(&self, handle: ModelHandle) -> Option<&str> {
+        self.node(handle).map(|node| node.name.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ModelHandle, &Model)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let handle = ModelHandle {
+                index: index as u32,
+                generation: slot.generation,
+            };
+            slot.node.as_ref().map(|node| (handle, &node.model))
+        })
+    }
+
+    // Attaches `child` to `parent`, or detaches it with `None`. The local
+    // transform of the child is kept, so it moves along with the new parent.
+    pub fn set_parent(
+        &mut self,
+        child: ModelHandle,
+        parent: Option<ModelHandle>,
+    ) -> Result<(), HierarchyError> {
+        if let Some(parent) = parent {
+            if self.node(parent).is_none() {
+                return Err(HierarchyError::StaleHandle(parent));
+            }
+            if self.is_descendant(parent, child) {
+                return Err(HierarchyError::Cycle(child));
+            }
         }
+        let node = self
+            .node_mut(child)
+            .ok_or(HierarchyError::StaleHandle(child))?;
+        node.parent = parent;
+        Ok(())
+    }
+
+    pub fn parent(&self, handle: ModelHandle) -> Option<ModelHandle> {
+        self.node(handle)?
+            .parent
+            .filter(|parent| self.node(*parent).is_some())
+    }
+
+    // True if `handle` is `ancestor` or below it in the hierarchy.
+    pub fn is_descendant(&self, handle: ModelHandle, ancestor: ModelHandle) -> bool {
+        std::iter::successors(Some(handle), |handle| self.parent(*handle)).any(|a| a == ancestor)
+    }
+
+    // Transform from the local space of the model into world space, `None`
+    // for a stale handle.
+    pub fn world_matrix(&self, handle: ModelHandle) -> Option<Mat4> {
+        let local = self.node(handle)?.model.get_model_matrix();
+        match self.parent(handle) {
+            Some(parent) => Some(self.world_matrix(parent)? * local),
+            None => Some(local),
+        }
+    }
+
+    // World matrices of all models, computing each shared ancestor only once.
+    pub fn world_matrices(&self) -> HashMap<ModelHandle, Mat4> {
+        let mut matrices = HashMap::new();
+        for (handle, _) in self.iter() {
+            self.cache_world_matrix(handle, &mut matrices);
+        }
+        matrices
+    }
+
+    fn cache_world_matrix(
+        &self,
+        handle: ModelHandle,
+        matrices: &mut HashMap<ModelHandle, Mat4>,
+    ) -> Option<Mat4> {
+        if let Some(matrix) = matrices.get(&handle) {
+            return Some(*matrix);
+        }
+        let local = self.node(handle)?.model.get_model_matrix();
+        let matrix = match self.parent(handle) {
+            Some(parent) => self.cache_world_matrix(parent, matrices)? * local,
+            None => local,
+        };
+        matrices.insert(handle, matrix);
+        Some(matrix)
+    }
+
+    fn unique_name(&self, name: String) -> String {
+        if !self.names.contains_key(&name) {
+            return name;
+        }
+        (1..)
+            .map(|i| format!("{name}#{i}"))
+            .find(|candidate| !self.names.contains_key(candidate))
+            .unwrap()
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Scene::new()
+    }
+}
+
+// Three cubes in front of the camera, used by the demo and as test fixture.
+pub fn get_cube_scene() -> Scene {
+    let mut scene = Scene::new();
+    let cube = model::get_cube_mesh();
+    let mut cube1 = Model::new(cube.clone());
+    cube1.transform.translate(Vec3 {
+        x: 0.0,
+        y: 0.0,
+        z: -5.0,
+    });
+    cube1.transform.rotate_around_axis(Vec3::Y, -0.3);
+    scene.insert("cube1", cube1);
+    let mut cube2 = Model::new(cube.clone());
+    cube2.transform.translate(Vec3 {
+        x: 3.0,
+        y: 0.0,
+        z: -5.0,
+    });
+    scene.insert("cube2", cube2);
+    let mut cube3 = Model::new(cube);
+    cube3.transform.translate(Vec3 {
+        x: -3.0,
+        y: 0.0,
+        z: -5.0,
+    });
+    scene.insert("cube3", cube3);
+    scene
+}
+
+// One of each procedural primitive in a row, for checking the generators.
+pub fn get_primitives_scene() -> Scene {
+    let meshes = [
+        ("cube", model::get_cube_mesh()),
+        ("uv_sphere", model::get_uv_sphere_mesh(32, 16)),
+        ("icosphere", model::get_icosphere_mesh(2)),
+        ("plane", model::get_plane_mesh(4)),
+        ("cylinder", model::get_cylinder_mesh(32)),
+        ("cone", model::get_cone_mesh(32)),
+        ("torus", model::get_torus_mesh(32, 16)),
+        ("capsule", model::get_capsule_mesh(32, 8)),
+    ];
+    let mut scene = Scene::new();
+    for (i, (name, mesh)) in meshes.into_iter().enumerate() {
+        let mut model = Model::new(mesh);
+        model
+            .transform
+            .translate(Vec3::new(-4.375 + 1.25 * i as f32, 0.0, -5.0));
+        model.transform.rotate_around_axis(Vec3::X, 0.4);
+        scene.insert(name, model);
+    }
+    scene
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn children_follow_parents() {
+        let mut scene = get_cube_scene();
+        let [cube1, cube2, cube3] = ["cube1", "cube2", "cube3"].map(|n| scene.find(n).unwrap());
+        scene.set_parent(cube2, Some(cube1)).unwrap();
+        scene.set_parent(cube3, Some(cube2)).unwrap();
+        assert_eq!(scene.parent(cube2), Some(cube1));
+        let origin = |scene: &Scene, handle| {
+            scene
+                .world_matrix(handle)
+                .unwrap()
+                .transform_point3(Vec3::ZERO)
+        };
+        let before = origin(&scene, cube3);
+        scene
+            .get_mut(cube1)
+            .unwrap()
+            .transform
+            .translate(Vec3::new(0.0, 1.0, 0.0));
+        assert!((origin(&scene, cube3) - before - Vec3::Y).length() < 1e-5);
+        let matrices = scene.world_matrices();
+        assert_eq!(Some(matrices[&cube3]), scene.world_matrix(cube3));
+        scene.remove(cube2);
+        assert_eq!(scene.parent(cube3), None);
+    }
+
+    #[test]
+    fn reject_invalid_parents() {
+        let mut scene = get_cube_scene();
+        let [cube1, cube2, cube3] = ["cube1", "cube2", "cube3"].map(|n| scene.find(n).unwrap());
+        scene.set_parent(cube2, Some(cube1)).unwrap();
+        assert_eq!(
+            scene.set_parent(cube1, Some(cube2)),
+            Err(HierarchyError::Cycle(cube1))
+        );
+        scene.remove(cube1);
+        assert_eq!(
+            scene.set_parent(cube3, Some(cube1)),
+            Err(HierarchyError::StaleHandle(cube1))
+        );
+        assert_eq!(scene.parent(cube3), None);
+    }
+
+    #[test]
+    fn stale_handles() {
+        let mut scene = get_cube_scene();
+        let cube2 = scene.find("cube2").unwrap();
+        assert!(scene.remove(cube2).is_some());
+        assert!(scene.remove(cube2).is_none());
+        let cube = scene.insert("cube1", Model::new(model::get_cube_mesh()));
+        assert_eq!(scene.name(cube), Some("cube1#1"));
+        // The new model reuses the slot of cube2, but not its handle.
+        assert!(scene.get(cube2).is_none());
+        assert!(scene.world_matrix(cube2).is_none());
+        let names = scene
+            .iter()
+            .map(|(handle, _)| scene.name(handle).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["cube1", "cube1#1", "cube3"]);
+        assert_eq!(scene.len(), 3);
     }
 }
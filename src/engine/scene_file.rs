@@ -0,0 +1,684 @@
+use glam::{EulerRot, Quat, Vec3};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::engine::camera::Camera;
+use crate::engine::gltf_loader;
+use crate::engine::light::{self, DEFAULT_AMBIENT_LIGHT, Light, LightKind};
+use crate::engine::material::Material;
+use crate::engine::mesh::{Mesh, MeshSource};
+use crate::engine::model::{self, Model};
+use crate::engine::obj;
+use crate::engine::scene::Scene;
+use crate::engine::texture::{Texture, TextureError};
+use crate::engine::transform::Transform;
+
+// Scenes are stored as RON, e.g.
+//
+// (
+//     camera: (eye: (0.0, 0.0, 1.0), target: (0.0, 0.0, 0.0), up: (0.0, 1.0, 0.0), fov_y: 1.57),
+//     ambient_light: (0.1, 0.1, 0.1),
+//     lights: [
+//         (kind: Directional, direction: (-1.0, -1.0, -1.0), intensity: 0.5),
+//         (kind: Point, position: (0.0, 2.0, -4.0), color: (1.0, 0.9, 0.7), attenuation: (1.0, 0.0, 0.1)),
+//         (kind: Spot(inner_angle: 0.3, outer_angle: 0.4), position: (0.0, 3.0, -5.0), direction: (0.0, -1.0, 0.0), cast_shadows: true),
+//     ],
+//     models: {
+//         "ball": (mesh: UvSphere(segments: 32, rings: 16), translation: (0.0, 0.0, -5.0)),
+//         "egg": (mesh: Icosphere(subdivisions: 2), scale: (1.0, 1.5, 1.0)),
+//         "teapot": (mesh: Obj(path: "teapot.obj", object: 0), color: (0.0, 1.0, 0.0)),
+//         "lamp": (mesh: Cube, material: (base_color: (1.0, 1.0, 1.0), emissive: (1.0, 0.8, 0.5))),
+//         "crate": (mesh: Cube, texture: "crate.png", color: (1.0, 1.0, 1.0)),
+//     },
+// )
+//
+// Paths of mesh and texture files are relative to the scene file. Omitted
+// translations and rotations are zero, omitted scales are one, omitted
+// material and light fields are those of the default material and light and
+// an omitted camera is the default camera. `color` is a shorthand for the base
+// color of the material, which the texture is multiplied with.
+#[derive(Serialize, Deserialize)]
+struct SceneFile {
+    #[serde(default)]
+    camera: CameraFile,
+    #[serde(default = "default_ambient_light")]
+    ambient_light: [f32; 3],
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    lights: Vec<LightFile>,
+    models: BTreeMap<String, ModelFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CameraFile {
+    eye: Vec3,
+    target: Vec3,
+    up: Vec3,
+    fov_y: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct LightFile {
+    kind: LightKind,
+    position: Vec3,
+    // Direction the light shines in, ignored by point lights.
+    direction: Vec3,
+    color: [f32; 3],
+    intensity: f32,
+    attenuation: [f32; 3],
+    cast_shadows: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ModelFile {
+    mesh: MeshSource,
+    #[serde(default)]
+    translation: Vec3,
+    // Euler angles in radians, applied in x, y, z order.
+    #[serde(default)]
+    rotation: Vec3,
+    #[serde(default = "default_scale")]
+    scale: Vec3,
+    #[serde(default)]
+    material: Material,
+    #[serde(default, with = "plain_some", skip_serializing_if = "Option::is_none")]
+    color: Option<[f32; 3]>,
+    // PNG or JPEG file.
+    #[serde(default, with = "plain_some", skip_serializing_if = "Option::is_none")]
+    texture: Option<PathBuf>,
+    // Name of the model the transform is relative to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent: Option<String>,
+}
+
+// Optional values that are written without `Some`, like the color shorthand,
+// which predates materials. `None` has to be skipped when serializing.
+mod plain_some {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer, T: Serialize>(
+        value: &Option<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => value.serialize(serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+        deserializer: D,
+    ) -> Result<Option<T>, D::Error> {
+        T::deserialize(deserializer).map(Some)
+    }
+}
+
+impl CameraFile {
+    fn new(camera: &Camera) -> Self {
+        let transform = &camera.transform;
+        CameraFile {
+            eye: transform.translation,
+            target: transform.translation + transform.forward(),
+            up: transform.up(),
+            fov_y: camera.fov_y,
+        }
+    }
+}
+
+impl Default for CameraFile {
+    fn default() -> Self {
+        CameraFile::new(&Camera::new(1.0))
+    }
+}
+
+impl LightFile {
+    fn new(light: &Light) -> Self {
+        LightFile {
+            kind: light.kind,
+            position: light.transform.translation,
+            direction: light.direction(),
+            color: light.color,
+            intensity: light.intensity,
+            attenuation: light.attenuation,
+            cast_shadows: light.cast_shadows,
+        }
+    }
+
+    fn light(&self) -> Light {
+        Light {
+            kind: self.kind,
+            transform: Transform {
+                translation: self.position,
+                rotation: light::rotation_towards(self.direction),
+                ..Transform::IDENTITY
+            },
+            color: self.color,
+            intensity: self.intensity,
+            attenuation: self.attenuation,
+            cast_shadows: self.cast_shadows,
+        }
+    }
+}
+
+impl Default for LightFile {
+    fn default() -> Self {
+        LightFile::new(&Light::DEFAULT)
+    }
+}
+
+fn default_scale() -> Vec3 {
+    Vec3::ONE
+}
+
+fn default_ambient_light() -> [f32; 3] {
+    DEFAULT_AMBIENT_LIGHT
+}
+
+#[derive(Debug)]
+pub enum SceneFileError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+    Gltf(gltf::Error),
+    Texture(TextureError),
+    // The referenced object or primitive does not exist in the mesh file.
+    MissingMesh(MeshSource),
+    // The mesh of the named model has no source and can't be saved.
+    NoMeshSource(String),
+    // The texture of the named model was not loaded from a file and can't be
+    // saved.
+    NoTexturePath(String),
+    // The parent of the named model does not exist or is a descendant of it.
+    InvalidParent(String),
+}
+
+impl fmt::Display for SceneFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneFileError::Io(e) => write!(f, "scene: {e}"),
+            SceneFileError::Parse(e) => write!(f, "scene: {e}"),
+            SceneFileError::Serialize(e) => write!(f, "scene: {e}"),
+            SceneFileError::Gltf(e) => write!(f, "scene: {e}"),
+            SceneFileError::Texture(e) => write!(f, "scene: {e}"),
+            SceneFileError::MissingMesh(source) => write!(f, "scene: mesh not found: {source:?}"),
+            SceneFileError::NoMeshSource(name) => {
+                write!(f, "scene: mesh of model {name} has no source")
+            }
+            SceneFileError::NoTexturePath(name) => {
+                write!(f, "scene: texture of model {name} has no path")
+            }
+            SceneFileError::InvalidParent(name) => {
+                write!(f, "scene: invalid parent of model {name}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneFileError {}
+
+impl From<io::Error> for SceneFileError {
+    fn from(e: io::Error) -> Self {
+        SceneFileError::Io(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for SceneFileError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        SceneFileError::Parse(e)
+    }
+}
+
+impl From<ron::Error> for SceneFileError {
+    fn from(e: ron::Error) -> Self {
+        SceneFileError::Serialize(e)
+    }
+}
+
+impl From<gltf::Error> for SceneFileError {
+    fn from(e: gltf::Error) -> Self {
+        SceneFileError::Gltf(e)
+    }
+}
+
+impl From<TextureError> for SceneFileError {
+    fn from(e: TextureError) -> Self {
+        SceneFileError::Texture(e)
+    }
+}
+
+pub fn load(path: impl AsRef<Path>) -> Result<Scene, SceneFileError> {
+    let path = path.as_ref();
+    parse(&fs::read_to_string(path)?, base_dir(path))
+}
+
+pub fn save(scene: &Scene, path: impl AsRef<Path>) -> Result<(), SceneFileError> {
+    let path = path.as_ref();
+    fs::write(path, write(scene, base_dir(path))?)?;
+    Ok(())
+}
+
+fn base_dir(path: &Path) -> &Path {
+    path.parent().unwrap_or(Path::new(""))
+}
+
+// Mesh and texture file paths are resolved relative to `base`.
+pub fn parse(source: &str, base: &Path) -> Result<Scene, SceneFileError> {
+    let file: SceneFile = ron::from_str(source)?;
+    let mut loader = AssetLoader::default();
+    let mut scene = Scene::new();
+    scene.camera.transform =
+        Transform::from_translation(file.camera.eye).looking_at(file.camera.target, file.camera.up);
+    scene.camera.fov_y = file.camera.fov_y;
+    scene.ambient_light = file.ambient_light;
+    scene.lights = file.lights.iter().map(LightFile::light).collect();
+    let mut parents = Vec::new();
+    for (name, model_file) in file.models {
+        let mut model = Model::new(loader.load(resolve(model_file.mesh, base))?);
+        let rotation = model_file.rotation;
+        model.transform = Transform {
+            translation: model_file.translation,
+            rotation: Quat::from_euler(EulerRot::XYZ, rotation.x, rotation.y, rotation.z),
+            scale: model_file.scale,
+        };
+        model.material = model_file.material;
+        if let Some(color) = model_file.color {
+            model.material.base_color = color;
+        }
+        model.texture = model_file
+            .texture
+            .map(|path| loader.load_texture(base.join(path)))
+            .transpose()?;
+        let handle = scene.insert(name.clone(), model);
+        if let Some(parent) = model_file.parent {
+            parents.push((name, handle, parent));
+        }
+    }
+    for (name, handle, parent) in parents {
+        let Some(parent) = scene.find(&parent) else {
+            return Err(SceneFileError::InvalidParent(name));
+        };
+        scene
+            .set_parent(handle, Some(parent))
+            .map_err(|_| SceneFileError::InvalidParent(name))?;
+    }
+    Ok(scene)
+}
+
+// Mesh and texture file paths are written relative to `base`.
+pub fn write(scene: &Scene, base: &Path) -> Result<String, SceneFileError> {
+    let mut file = SceneFile {
+        camera: CameraFile::new(&scene.camera),
+        ambient_light: scene.ambient_light,
+        lights: scene.lights.iter().map(LightFile::new).collect(),
+        models: BTreeMap::new(),
+    };
+    for (handle, model) in scene.iter() {
+        let name = scene.name(handle).unwrap();
+        let source = model
+            .mesh()
+            .source()
+            .ok_or_else(|| SceneFileError::NoMeshSource(name.to_string()))?;
+        let texture = match &model.texture {
+            Some(texture) => {
+                let path = texture
+                    .path()
+                    .ok_or_else(|| SceneFileError::NoTexturePath(name.to_string()))?;
+                Some(relative_path(path.to_path_buf(), base))
+            }
+            None => None,
+        };
+        let model_file = ModelFile {
+            mesh: relativize(source.clone(), base),
+            translation: model.transform.translation,
+            rotation: model.transform.rotation.to_euler(EulerRot::XYZ).into(),
+            scale: model.transform.scale,
+            material: model.material,
+            color: None,
+            texture,
+            parent: scene
+                .parent(handle)
+                .and_then(|parent| scene.name(parent))
+                .map(str::to_string),
+        };
+        file.models.insert(name.to_string(), model_file);
+    }
+    Ok(ron::ser::to_string_pretty(
+        &file,
+        ron::ser::PrettyConfig::default(),
+    )?)
+}
+
+fn resolve(source: MeshSource, base: &Path) -> MeshSource {
+    map_path(source, |path| base.join(path))
+}
+
+fn relativize(source: MeshSource, base: &Path) -> MeshSource {
+    map_path(source, |path| relative_path(path, base))
+}
+
+// `path` relative to `base`, with `..` for paths outside of it, so `resolve`
+// finds the file again. Paths that can't be resolved, e.g. of removed files,
+// or that share no root with `base` are made absolute.
+fn relative_path(path: PathBuf, base: &Path) -> PathBuf {
+    if let Ok(relative) = path.strip_prefix(base) {
+        return relative.to_path_buf();
+    }
+    let base = if base.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        base
+    };
+    let (Ok(canonical), Ok(base)) = (path.canonicalize(), base.canonicalize()) else {
+        return std::path::absolute(&path).unwrap_or(path);
+    };
+    let common = canonical
+        .components()
+        .zip(base.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common == 0 {
+        return canonical;
+    }
+    let mut relative = PathBuf::new();
+    for _ in base.components().skip(common) {
+        relative.push("..");
+    }
+    relative.extend(canonical.components().skip(common));
+    relative
+}
+
+fn map_path(source: MeshSource, f: impl FnOnce(PathBuf) -> PathBuf) -> MeshSource {
+    match source {
+        MeshSource::Obj { path, object } => MeshSource::Obj {
+            path: f(path),
+            object,
+        },
+        MeshSource::Gltf {
+            path,
+            mesh,
+            primitive,
+        } => MeshSource::Gltf {
+            path: f(path),
+            mesh,
+            primitive,
+        },
+        source => source,
+    }
+}
+
+// Creates every mesh only once, so models with the same source share it,
+// and reads every mesh and texture file only once.
+#[derive(Default)]
+struct AssetLoader {
+    meshes: HashMap<MeshSource, Arc<Mesh>>,
+    files: HashMap<PathBuf, Vec<Arc<Mesh>>>,
+    textures: HashMap<PathBuf, Arc<Texture>>,
+}
+
+impl AssetLoader {
+    fn load(&mut self, source: MeshSource) -> Result<Arc<Mesh>, SceneFileError> {
+        if let Some(mesh) = self.meshes.get(&source) {
+            return Ok(mesh.clone());
+        }
+        let mesh = match &source {
+            MeshSource::Cube => model::get_cube_mesh(),
+            &MeshSource::UvSphere { segments, rings } => model::get_uv_sphere_mesh(segments, rings),
+            &MeshSource::Icosphere { subdivisions } => model::get_icosphere_mesh(subdivisions),
+            &MeshSource::Plane { subdivisions } => model::get_plane_mesh(subdivisions),
+            &MeshSource::Cylinder { segments } => model::get_cylinder_mesh(segments),
+            &MeshSource::Cone { segments } => model::get_cone_mesh(segments),
+            &MeshSource::Torus {
+                ring_segments,
+                tube_segments,
+            } => model::get_torus_mesh(ring_segments, tube_segments),
+            &MeshSource::Capsule { segments, rings } => model::get_capsule_mesh(segments, rings),
+            MeshSource::Obj { path, .. } | MeshSource::Gltf { path, .. } => self
+                .load_file(path)?
+                .iter()
+                .find(|mesh| mesh.source() == Some(&source))
+                .cloned()
+                .ok_or_else(|| SceneFileError::MissingMesh(source.clone()))?,
+        };
+        self.meshes.insert(source, mesh.clone());
+        Ok(mesh)
+    }
+
+    fn load_file(&mut self, path: &Path) -> Result<&[Arc<Mesh>], SceneFileError> {
+        if !self.files.contains_key(path) {
+            let meshes = if path.extension().is_some_and(|e| e == "obj") {
+                obj::load(path)?
+                    .into_iter()
+                    .map(|object| object.model.mesh().clone())
+                    .collect()
+            } else {
+                gltf_loader::load_meshes(path)?.concat()
+            };
+            self.files.insert(path.to_path_buf(), meshes);
+        }
+        Ok(&self.files[path])
+    }
+
+    fn load_texture(&mut self, path: PathBuf) -> Result<Arc<Texture>, SceneFileError> {
+        if let Some(texture) = self.textures.get(&path) {
+            return Ok(texture.clone());
+        }
+        let texture = Arc::new(Texture::load(&path)?);
+        self.textures.insert(path, texture.clone());
+        Ok(texture)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::model::DEFAULT_COLOR;
+    use crate::engine::scene;
+
+    fn model<'a>(scene: &'a Scene, name: &str) -> &'a Model {
+        scene.get(scene.find(name).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn write_and_parse() {
+        let mut original = scene::get_primitives_scene();
+        original
+            .camera
+            .transform
+            .look_at(Vec3::new(1.0, 2.0, 3.0), Vec3::Y);
+        let torus = original.find("torus").unwrap();
+        let torus = original.get_mut(torus).unwrap();
+        torus.material.base_color = [0.0, 0.5, 1.0];
+        torus.material.emissive = [0.1, 0.0, 0.0];
+        torus.transform.scale = Vec3::new(1.0, 2.0, 0.5);
+        original.lights = vec![
+            Light::directional(Vec3::new(-1.0, -1.0, 0.0)),
+            Light {
+                color: [1.0, 0.5, 0.0],
+                attenuation: [1.0, 0.0, 0.2],
+                cast_shadows: true,
+                ..Light::spot(Vec3::Y, Vec3::NEG_Y, 0.3, 0.4)
+            },
+        ];
+        let source = write(&original, Path::new("")).unwrap();
+        let scene = parse(&source, Path::new("")).unwrap();
+        assert_eq!(scene.len(), original.len());
+        let camera = scene.camera.view();
+        assert!(camera.abs_diff_eq(original.camera.view(), 1e-5));
+        assert_eq!(scene.lights.len(), 2);
+        for (loaded, light) in scene.lights.iter().zip(&original.lights) {
+            assert_eq!(loaded.kind, light.kind);
+            assert_eq!(loaded.color, light.color);
+            assert_eq!(loaded.attenuation, light.attenuation);
+            assert_eq!(loaded.cast_shadows, light.cast_shadows);
+            assert!(loaded.direction().abs_diff_eq(light.direction(), 1e-5));
+        }
+        for (handle, model) in original.iter() {
+            let loaded = self::model(&scene, original.name(handle).unwrap());
+            assert_eq!(loaded.mesh().source(), model.mesh().source());
+            assert_eq!(
+                loaded.mesh().positions().len(),
+                model.mesh().positions().len()
+            );
+            let matrix = loaded.get_model_matrix();
+            assert!(matrix.abs_diff_eq(model.get_model_matrix(), 1e-5));
+            assert_eq!(loaded.material, model.material);
+        }
+    }
+
+    #[test]
+    fn parents() {
+        let source = r#"(
+            models: {
+                "car": (mesh: Cube),
+                "wheel": (mesh: Cylinder(segments: 16), parent: Some("car")),
+            },
+        )"#;
+        let scene = parse(source, Path::new("")).unwrap();
+        let wheel = scene.find("wheel").unwrap();
+        assert_eq!(scene.parent(wheel), scene.find("car"));
+        let written = write(&scene, Path::new("")).unwrap();
+        assert_eq!(written.matches("parent").count(), 1);
+        let cycle = source.replace(r#"(mesh: Cube)"#, r#"(mesh: Cube, parent: Some("wheel"))"#);
+        let error = parse(&cycle, Path::new("")).err().unwrap();
+        assert!(matches!(error, SceneFileError::InvalidParent(_)));
+    }
+
+    #[test]
+    fn load_cubes_scene() {
+        let scene = load("scenes/cubes.ron").unwrap();
+        let cubes = scene::get_cube_scene();
+        assert_eq!(scene.len(), cubes.len());
+        for (handle, model) in cubes.iter() {
+            let matrix = self::model(&scene, cubes.name(handle).unwrap()).get_model_matrix();
+            assert!(matrix.abs_diff_eq(model.get_model_matrix(), 1e-5));
+        }
+    }
+
+    #[test]
+    fn load_lights_scene() {
+        let scene = load("scenes/lights.ron").unwrap();
+        let kinds = scene
+            .lights
+            .iter()
+            .map(|light| light.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                LightKind::Directional,
+                LightKind::Point,
+                LightKind::Spot {
+                    inner_angle: 0.25,
+                    outer_angle: 0.35,
+                },
+            ]
+        );
+        assert!(scene.lights[2].direction().abs_diff_eq(Vec3::NEG_Y, 1e-5));
+    }
+
+    #[test]
+    fn share_meshes() {
+        let source = r#"(
+            models: {
+                "a": (mesh: Icosphere(subdivisions: 1)),
+                "b": (mesh: Icosphere(subdivisions: 1), translation: (1.0, 0.0, 0.0)),
+                "c": (mesh: Icosphere(subdivisions: 2), color: (0.0, 1.0, 0.0)),
+            },
+        )"#;
+        let scene = parse(source, Path::new("")).unwrap();
+        let [a, b, c] = ["a", "b", "c"].map(|name| model(&scene, name).mesh());
+        assert!(Arc::ptr_eq(a, b));
+        assert!(!Arc::ptr_eq(a, c));
+        assert_eq!(model(&scene, "a").material.base_color, DEFAULT_COLOR);
+        assert_eq!(model(&scene, "c").material.base_color, [0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn save_files_outside_scene_dir() {
+        // Relative like paths given on the command line.
+        let dir = Path::new("target").join(format!("scene-test-{}", std::process::id()));
+        let mesh_path = dir.join("meshes/cubes.obj");
+        let texture_path = dir.join("textures/green.png");
+        let scene_path = dir.join("scenes/cubes.ron");
+        for path in [&mesh_path, &texture_path, &scene_path] {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+        }
+        obj::save(&scene::get_cube_scene(), &mesh_path).unwrap();
+        image::RgbaImage::from_pixel(2, 2, image::Rgba([0, 255, 0, 255]))
+            .save(&texture_path)
+            .unwrap();
+        let mut original = obj::load_scene(&mesh_path).unwrap();
+        let (textured, _) = original.iter().next().unwrap();
+        original.get_mut(textured).unwrap().texture =
+            Some(Arc::new(Texture::load(&texture_path).unwrap()));
+        save(&original, &scene_path).unwrap();
+        let source = fs::read_to_string(&scene_path).unwrap();
+        let scene = load(&scene_path);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(source.contains("../meshes/cubes.obj"));
+        assert!(source.contains("../textures/green.png"));
+        let scene = scene.unwrap();
+        assert_eq!(scene.len(), original.len());
+        for (handle, model) in original.iter() {
+            let loaded = self::model(&scene, original.name(handle).unwrap());
+            assert_eq!(
+                loaded.mesh().positions().len(),
+                model.mesh().positions().len()
+            );
+            let matrix = loaded.get_model_matrix();
+            assert!(matrix.abs_diff_eq(model.get_model_matrix(), 1e-5));
+            assert_eq!(loaded.texture.is_some(), handle == textured);
+        }
+        let texture = self::model(&scene, original.name(textured).unwrap())
+            .texture
+            .clone()
+            .unwrap();
+        assert_eq!(&texture.pixels()[..4], &[0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn relative_paths() {
+        let source = MeshSource::Obj {
+            path: PathBuf::from("scenes/meshes/teapot.obj"),
+            object: 0,
+        };
+        let relative = relativize(source.clone(), Path::new("scenes"));
+        assert_eq!(
+            relative,
+            MeshSource::Obj {
+                path: PathBuf::from("meshes/teapot.obj"),
+                object: 0,
+            }
+        );
+        assert_eq!(resolve(relative, Path::new("scenes")), source);
+    }
+
+    #[test]
+    fn share_textures() {
+        let base = std::env::temp_dir().join("magma_share_textures");
+        fs::create_dir_all(&base).unwrap();
+        image::RgbaImage::from_pixel(2, 2, image::Rgba([0, 255, 0, 255]))
+            .save(base.join("green.png"))
+            .unwrap();
+        let source = r#"(
+            models: {
+                "a": (mesh: Cube, texture: "green.png"),
+                "b": (mesh: Cube, texture: "green.png"),
+            },
+        )"#;
+        let scene = parse(source, &base).unwrap();
+        let [a, b] = ["a", "b"].map(|name| model(&scene, name).texture.clone().unwrap());
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(&a.pixels()[..4], &[0, 255, 0, 255]);
+        assert!(
+            write(&scene, &base)
+                .unwrap()
+                .contains(r#"texture: "green.png""#)
+        );
+    }
+}
@@ -5,6 +5,13 @@ pub mod mesh_vs {
     }
 }
 
+pub mod shadow_vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "src/shader/shadow_vert.glsl",
+    }
+}
+
 pub mod mesh_fs {
     vulkano_shaders::shader! {
         ty: "fragment",
@@ -0,0 +1,302 @@
+use glam::{Mat4, Vec3};
+use std::sync::Arc;
+use vulkano::buffer::Subbuffer;
+use vulkano::command_buffer::{
+    AutoCommandBufferBuilder, PrimaryAutoCommandBuffer, RenderPassBeginInfo,
+};
+use vulkano::device::Device;
+use vulkano::format::Format;
+use vulkano::image::sampler::{Sampler, SamplerAddressMode, SamplerCreateInfo};
+use vulkano::image::view::{ImageView, ImageViewCreateInfo, ImageViewType};
+use vulkano::image::{Image, ImageCreateInfo, ImageSubresourceRange, ImageType, ImageUsage};
+use vulkano::memory::allocator::{AllocationCreateInfo, StandardMemoryAllocator};
+use vulkano::pipeline::graphics::GraphicsPipelineCreateInfo;
+use vulkano::pipeline::graphics::depth_stencil::{CompareOp, DepthState, DepthStencilState};
+use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
+use vulkano::pipeline::graphics::multisample::MultisampleState;
+use vulkano::pipeline::graphics::rasterization::{DepthBiasState, RasterizationState};
+use vulkano::pipeline::graphics::vertex_input::{Vertex, VertexDefinition};
+use vulkano::pipeline::graphics::viewport::ViewportState;
+use vulkano::pipeline::layout::PipelineDescriptorSetLayoutCreateInfo;
+use vulkano::pipeline::{
+    DynamicState, GraphicsPipeline, Pipeline, PipelineLayout, PipelineShaderStageCreateInfo,
+};
+use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass};
+use vulkano::shader::EntryPoint;
+
+use crate::engine::error::MagmaError;
+use crate::engine::light::{Light, LightKind};
+use crate::engine::mesh::Position;
+use crate::engine::mesh_cache::GpuMesh;
+use crate::engine::model::InstanceData;
+use crate::engine::scene::Scene;
+use crate::engine::shader;
+
+// Shadow casting lights beyond this number cast no shadows.
+pub const MAX_SHADOW_MAPS: usize = 4;
+
+// Width and height of every shadow map in texels.
+const SHADOW_MAP_SIZE: u32 = 2048;
+
+const SHADOW_MAP_FORMAT: Format = Format::D16_UNORM;
+
+// Depth-only pass that renders the scene from every shadow casting light into
+// one layer of an array image, which the main pass samples to find out
+// whether a fragment is lit.
+pub struct ShadowPass {
+    pipeline: Arc<GraphicsPipeline>,
+    // One per layer of the shadow map image.
+    framebuffers: Vec<Arc<Framebuffer>>,
+    // All layers, sampled with depth comparison.
+    pub view: Arc<ImageView>,
+    pub sampler: Arc<Sampler>,
+}
+
+impl ShadowPass {
+    pub fn new(
+        device: &Arc<Device>,
+        memory_allocator: &Arc<StandardMemoryAllocator>,
+    ) -> Result<Self, MagmaError> {
+        let render_pass = vulkano::single_pass_renderpass!(
+            device.clone(),
+            attachments: {
+                depth: {
+                    format: SHADOW_MAP_FORMAT,
+                    samples: 1,
+                    load_op: Clear,
+                    store_op: Store,
+                },
+            },
+            pass: {
+                color: [],
+                depth_stencil: {depth},
+            },
+        )?;
+        let vertex_shader =
+            super::load_shader("shadow_vs", shader::shadow_vs::load(device.clone()))?;
+        let pipeline = create_pipeline(device, &render_pass, vertex_shader)?;
+        let image = Image::new(
+            memory_allocator.clone(),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: SHADOW_MAP_FORMAT,
+                extent: [SHADOW_MAP_SIZE, SHADOW_MAP_SIZE, 1],
+                array_layers: MAX_SHADOW_MAPS as u32,
+                usage: ImageUsage::DEPTH_STENCIL_ATTACHMENT | ImageUsage::SAMPLED,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        )?;
+        let framebuffers = (0..MAX_SHADOW_MAPS as u32)
+            .map(|layer| {
+                let view = ImageView::new(
+                    image.clone(),
+                    ImageViewCreateInfo {
+                        view_type: ImageViewType::Dim2d,
+                        subresource_range: ImageSubresourceRange {
+                            array_layers: layer..layer + 1,
+                            ..image.subresource_range()
+                        },
+                        ..ImageViewCreateInfo::from_image(&image)
+                    },
+                )?;
+                let framebuffer = Framebuffer::new(
+                    render_pass.clone(),
+                    FramebufferCreateInfo {
+                        attachments: vec![view],
+                        ..Default::default()
+                    },
+                )?;
+                Ok(framebuffer)
+            })
+            .collect::<Result<Vec<_>, MagmaError>>()?;
+        // Nearest filtering, since linear filtering of depth formats is
+        // optional. The shader filters by sampling several texels instead.
+        let sampler = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                compare: Some(CompareOp::LessOrEqual),
+                ..Default::default()
+            },
+        )?;
+        Ok(ShadowPass {
+            pipeline,
+            framebuffers,
+            view: ImageView::new_default(image)?,
+            sampler,
+        })
+    }
+
+    // Renders the batches into the shadow map layers of the lights, given as
+    // layer and matrix from world space to the clip space of the shadow map.
+    // Must be recorded outside of any render pass.
+    pub fn record(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        shadows: &[(u32, Mat4)],
+        batches: &[(GpuMesh, Subbuffer<[InstanceData]>)],
+    ) -> Result<(), MagmaError> {
+        for &(layer, light_matrix) in shadows {
+            let framebuffer = self.framebuffers[layer as usize].clone();
+            builder
+                .begin_render_pass(
+                    RenderPassBeginInfo {
+                        clear_values: vec![Some(1f32.into())],
+                        ..RenderPassBeginInfo::framebuffer(framebuffer.clone())
+                    },
+                    Default::default(),
+                )?
+                .bind_pipeline_graphics(self.pipeline.clone())?;
+            super::set_viewport(builder, [0, 0], framebuffer.extent())?;
+            let constants = shader::shadow_vs::Shadow {
+                light_matrix: light_matrix.to_cols_array_2d(),
+            };
+            builder.push_constants(self.pipeline.layout().clone(), 0, constants)?;
+            for (mesh, instances) in batches {
+                builder
+                    .bind_vertex_buffers(0, (mesh.positions.clone(), instances.clone()))?
+                    .bind_index_buffer(mesh.indices.clone())?;
+                unsafe {
+                    builder.draw_indexed(mesh.indices.len() as u32, instances.len() as u32, 0, 0, 0)
+                }?;
+            }
+            builder.end_render_pass(Default::default())?;
+        }
+        Ok(())
+    }
+}
+
+fn create_pipeline(
+    device: &Arc<Device>,
+    render_pass: &Arc<RenderPass>,
+    vs: EntryPoint,
+) -> Result<Arc<GraphicsPipeline>, MagmaError> {
+    let vertex_input_state =
+        [Position::per_vertex(), InstanceData::per_instance()].definition(&vs)?;
+    let stages = [PipelineShaderStageCreateInfo::new(vs)];
+    let layout = PipelineLayout::new(
+        device.clone(),
+        PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages)
+            .into_pipeline_layout_create_info(device.clone())?,
+    )?;
+    // The shadow render pass has a single subpass.
+    let subpass =
+        Subpass::from(render_pass.clone(), 0).expect("engine: render pass has no subpass");
+    let pipeline = GraphicsPipeline::new(
+        device.clone(),
+        None,
+        GraphicsPipelineCreateInfo {
+            stages: stages.into_iter().collect(),
+            vertex_input_state: Some(vertex_input_state),
+            input_assembly_state: Some(InputAssemblyState::default()),
+            viewport_state: Some(ViewportState::default()),
+            // Offsets the depth of surfaces facing the light, so they don't
+            // shadow themselves due to the limited resolution of the map.
+            rasterization_state: Some(RasterizationState {
+                depth_bias: Some(DepthBiasState {
+                    constant_factor: 2.0,
+                    clamp: 0.0,
+                    slope_factor: 2.0,
+                }),
+                ..Default::default()
+            }),
+            depth_stencil_state: Some(DepthStencilState {
+                depth: Some(DepthState::simple()),
+                ..Default::default()
+            }),
+            multisample_state: Some(MultisampleState::default()),
+            dynamic_state: [DynamicState::Viewport, DynamicState::Scissor]
+                .into_iter()
+                .collect(),
+            subpass: Some(subpass.into()),
+            ..GraphicsPipelineCreateInfo::layout(layout)
+        },
+    )?;
+    Ok(pipeline)
+}
+
+// Center and radius of a sphere around all models in world space, `None`
+// for an empty scene.
+pub fn scene_bounds(scene: &Scene) -> Option<(Vec3, f32)> {
+    let world_matrices = scene.world_matrices();
+    let spheres = scene
+        .iter()
+        .map(|(handle, model)| {
+            let (scale, _, translation) = world_matrices[&handle].to_scale_rotation_translation();
+            (
+                translation,
+                model.mesh().radius() * scale.abs().max_element(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let min = spheres
+        .iter()
+        .map(|&(center, radius)| center - Vec3::splat(radius))
+        .reduce(Vec3::min)?;
+    let max = spheres
+        .iter()
+        .map(|&(center, radius)| center + Vec3::splat(radius))
+        .reduce(Vec3::max)?;
+    Some(((min + max) / 2.0, (max - min).length() / 2.0))
+}
+
+// Matrix from world space to the clip space of the shadow map of the light,
+// which covers the scene bounds. `None` for lights without shadows.
+pub fn light_matrix(light: &Light, bounds: (Vec3, f32)) -> Option<Mat4> {
+    let (center, radius) = bounds;
+    if !light.cast_shadows || radius <= 0.0 {
+        return None;
+    }
+    let direction = light.direction();
+    let up = if direction.y.abs() > 0.99 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    };
+    match light.kind {
+        LightKind::Directional => {
+            let view = Mat4::look_to_rh(center - radius * direction, direction, up);
+            let projection =
+                Mat4::orthographic_rh(-radius, radius, -radius, radius, 0.0, 2.0 * radius);
+            Some(projection * view)
+        }
+        LightKind::Spot { outer_angle, .. } => {
+            const NEAR: f32 = 0.1;
+            let position = light.transform.translation;
+            let view = Mat4::look_to_rh(position, direction, up);
+            let far = (center.distance(position) + radius).max(2.0 * NEAR);
+            // The field of view must stay below 180 degrees.
+            let fov = (2.0 * outer_angle).min(3.0);
+            Some(Mat4::perspective_rh(fov, 1.0, NEAR, far) * view)
+        }
+        LightKind::Point => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::scene;
+
+    #[test]
+    fn directional_light_covers_scene() {
+        let scene = scene::get_cube_scene();
+        let bounds = scene_bounds(&scene).unwrap();
+        let light = Light {
+            cast_shadows: true,
+            ..Light::directional(Vec3::new(-1.0, -1.0, 0.0))
+        };
+        let matrix = light_matrix(&light, bounds).unwrap();
+        for (handle, _) in scene.iter() {
+            let center = scene
+                .world_matrix(handle)
+                .unwrap()
+                .transform_point3(Vec3::ZERO);
+            let clip = matrix.project_point3(center);
+            assert!(clip.x.abs() <= 1.0 && clip.y.abs() <= 1.0);
+            assert!((0.0..=1.0).contains(&clip.z));
+        }
+        assert_eq!(light_matrix(&Light::point(Vec3::ZERO), bounds), None);
+    }
+}
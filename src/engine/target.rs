@@ -0,0 +1,199 @@
+use std::sync::Arc;
+use vulkano::device::{Device, DeviceOwned};
+use vulkano::format::Format;
+use vulkano::format::NumericFormat;
+use vulkano::image::Image;
+use vulkano::image::ImageCreateInfo;
+use vulkano::image::ImageType;
+use vulkano::image::ImageUsage;
+use vulkano::memory::allocator::AllocationCreateInfo;
+use vulkano::memory::allocator::StandardMemoryAllocator;
+use vulkano::swapchain::ColorSpace;
+use vulkano::swapchain::PresentMode;
+use vulkano::swapchain::Surface;
+use vulkano::swapchain::Swapchain;
+use vulkano::swapchain::SwapchainCreateInfo;
+use winit::window::Window;
+
+use crate::engine::error::MagmaError;
+
+// Color format of offscreen render targets, sRGB like the default swapchain
+// format, so headless frames look like those shown in a window.
+pub const OFFSCREEN_FORMAT: Format = Format::R8G8B8A8_SRGB;
+
+// Kind of swapchain format to look for. If the surface supports none of that
+// kind, its first format is used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FormatPreference {
+    // 8 bits per channel with sRGB encoding.
+    #[default]
+    Srgb,
+    // 10 bits per channel, which reduces banding in gradients, otherwise
+    // sRGB. The color space stays sRGB, there is no HDR output.
+    TenBit,
+}
+
+// How images are presented to a window. Present modes the surface does not
+// support fall back to FIFO, which is always available.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapchainConfig {
+    pub format: FormatPreference,
+    // `Fifo` waits for vertical sync, `Mailbox` replaces queued images
+    // without tearing and `Immediate` presents right away.
+    pub present_mode: PresentMode,
+}
+
+impl Default for SwapchainConfig {
+    fn default() -> Self {
+        SwapchainConfig {
+            format: FormatPreference::Srgb,
+            present_mode: PresentMode::Fifo,
+        }
+    }
+}
+
+// Destination of the color attachment of the render pass.
+pub enum RenderTarget {
+    // Images are presented to a window surface.
+    Window {
+        window: Arc<Window>,
+        swapchain: Arc<Swapchain>,
+    },
+    // A single image that is never presented, used without a window system.
+    Offscreen {
+        image: Arc<Image>,
+    },
+}
+
+impl RenderTarget {
+    pub fn format(&self) -> Format {
+        match self {
+            RenderTarget::Window { swapchain, .. } => swapchain.image_format(),
+            RenderTarget::Offscreen { image } => image.format(),
+        }
+    }
+
+    pub fn extent(&self) -> [u32; 2] {
+        match self {
+            RenderTarget::Window { swapchain, .. } => swapchain.image_extent(),
+            RenderTarget::Offscreen { image } => {
+                let extent = image.extent();
+                [extent[0], extent[1]]
+            }
+        }
+    }
+}
+
+pub fn create_swapchain(
+    device: &Arc<Device>,
+    surface: Arc<Surface>,
+    image_extent: [u32; 2],
+    config: SwapchainConfig,
+) -> Result<(Arc<Swapchain>, Vec<Arc<Image>>), MagmaError> {
+    let surface_capabilities = device
+        .physical_device()
+        .surface_capabilities(&surface, Default::default())?;
+    let (image_format, image_color_space) = choose_format(device, &surface, config.format)?;
+    let present_mode = choose_present_mode(device, &surface, config.present_mode)?;
+    let swapchain = Swapchain::new(
+        device.clone(),
+        surface,
+        SwapchainCreateInfo {
+            min_image_count: surface_capabilities.min_image_count.max(2),
+            image_format,
+            image_color_space,
+            image_extent,
+            // Transfers from swapchain images are needed for frame capture.
+            image_usage: ImageUsage::COLOR_ATTACHMENT
+                | (surface_capabilities.supported_usage_flags & ImageUsage::TRANSFER_SRC),
+            // Vulkan requires surfaces to support at least one mode.
+            composite_alpha: surface_capabilities
+                .supported_composite_alpha
+                .into_iter()
+                .next()
+                .expect("engine: surface supports no composite alpha mode"),
+            present_mode,
+            ..Default::default()
+        },
+    )?;
+    Ok(swapchain)
+}
+
+// Recreates the swapchain with a new extent and configuration.
+pub fn recreate_swapchain(
+    swapchain: &Arc<Swapchain>,
+    image_extent: [u32; 2],
+    config: SwapchainConfig,
+) -> Result<(Arc<Swapchain>, Vec<Arc<Image>>), MagmaError> {
+    let device = swapchain.device();
+    let surface = swapchain.surface();
+    let (image_format, image_color_space) = choose_format(device, surface, config.format)?;
+    let present_mode = choose_present_mode(device, surface, config.present_mode)?;
+    let recreated = swapchain.recreate(SwapchainCreateInfo {
+        image_format,
+        image_color_space,
+        image_extent,
+        present_mode,
+        ..swapchain.create_info()
+    })?;
+    Ok(recreated)
+}
+
+fn choose_format(
+    device: &Arc<Device>,
+    surface: &Surface,
+    preference: FormatPreference,
+) -> Result<(Format, ColorSpace), MagmaError> {
+    let formats = device
+        .physical_device()
+        .surface_formats(surface, Default::default())?;
+    let is_srgb = |&(format, color_space): &(Format, ColorSpace)| {
+        color_space == ColorSpace::SrgbNonLinear
+            && format.numeric_format_color() == Some(NumericFormat::SRGB)
+            && format.components()[..3] == [8, 8, 8]
+    };
+    let is_10_bit = |&(format, color_space): &(Format, ColorSpace)| {
+        color_space == ColorSpace::SrgbNonLinear && format.components()[..3] == [10, 10, 10]
+    };
+    let chosen = match preference {
+        FormatPreference::Srgb => formats.iter().find(|f| is_srgb(f)),
+        FormatPreference::TenBit => formats
+            .iter()
+            .find(|f| is_10_bit(f))
+            .or_else(|| formats.iter().find(|f| is_srgb(f))),
+    };
+    Ok(*chosen.unwrap_or(&formats[0]))
+}
+
+fn choose_present_mode(
+    device: &Arc<Device>,
+    surface: &Surface,
+    preference: PresentMode,
+) -> Result<PresentMode, MagmaError> {
+    let present_modes = device
+        .physical_device()
+        .surface_present_modes(surface, Default::default())?;
+    if present_modes.contains(&preference) {
+        Ok(preference)
+    } else {
+        Ok(PresentMode::Fifo)
+    }
+}
+
+pub fn create_offscreen_image(
+    memory_allocator: &Arc<StandardMemoryAllocator>,
+    extent: [u32; 2],
+) -> Result<Arc<Image>, MagmaError> {
+    let image = Image::new(
+        memory_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: OFFSCREEN_FORMAT,
+            extent: [extent[0], extent[1], 1],
+            usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    )?;
+    Ok(image)
+}
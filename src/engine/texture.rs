@@ -0,0 +1,107 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Image with four 8 bit sRGB channels (RGBA) that is shared between models
+// through an `Arc` like `Mesh`, so it is uploaded to the GPU only once.
+pub struct Texture {
+    id: u64,
+    path: Option<PathBuf>,
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum TextureError {
+    Image(image::ImageError),
+    // The width or the height is zero.
+    Empty,
+    // The pixel data does not hold four bytes per pixel.
+    PixelCount { expected: usize, actual: usize },
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextureError::Image(e) => write!(f, "texture: {e}"),
+            TextureError::Empty => write!(f, "texture: needs at least one pixel"),
+            TextureError::PixelCount { expected, actual } => {
+                write!(
+                    f,
+                    "texture: expected {expected} bytes of pixels, got {actual}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for TextureError {}
+
+impl From<image::ImageError> for TextureError {
+    fn from(e: image::ImageError) -> Self {
+        TextureError::Image(e)
+    }
+}
+
+impl Texture {
+    // `pixels` holds the rows from top to bottom, four bytes per pixel.
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Result<Self, TextureError> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        if width == 0 || height == 0 {
+            return Err(TextureError::Empty);
+        }
+        let expected = width as usize * height as usize * 4;
+        if pixels.len() != expected {
+            return Err(TextureError::PixelCount {
+                expected,
+                actual: pixels.len(),
+            });
+        }
+        Ok(Texture {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            path: None,
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    // Decodes a PNG or JPEG file. The path is recorded, so scene files can
+    // refer to the file instead of storing the pixels.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TextureError> {
+        let path = path.as_ref();
+        let image = image::open(path)?.into_rgba8();
+        let (width, height) = image.dimensions();
+        let mut texture = Texture::new(width, height, image.into_raw())?;
+        texture.path = Some(path.to_path_buf());
+        Ok(texture)
+    }
+
+    // A single white pixel, which leaves the base color unchanged.
+    pub fn white() -> Self {
+        // One pixel of four bytes is always a valid texture.
+        Texture::new(1, 1, vec![255; 4]).expect("texture: white pixel is valid")
+    }
+
+    // Unique for every texture, used to look up its GPU image.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+}
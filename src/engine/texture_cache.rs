@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage};
+use vulkano::command_buffer::{
+    AutoCommandBufferBuilder, BlitImageInfo, CopyBufferToImageInfo, ImageBlit,
+    PrimaryAutoCommandBuffer,
+};
+use vulkano::format::Format;
+use vulkano::image::sampler::Filter;
+use vulkano::image::view::ImageView;
+use vulkano::image::{
+    Image, ImageCreateInfo, ImageSubresourceLayers, ImageType, ImageUsage, max_mip_levels,
+    mip_level_extent,
+};
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator};
+
+use crate::engine::error::MagmaError;
+use crate::engine::texture::Texture;
+
+// Keeps uploaded textures alive across frames, keyed by texture id, like
+// `MeshCache` does for geometry.
+pub struct TextureCache {
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    textures: HashMap<u64, Arc<ImageView>>,
+}
+
+impl TextureCache {
+    pub fn new(memory_allocator: Arc<StandardMemoryAllocator>) -> Self {
+        TextureCache {
+            memory_allocator,
+            textures: HashMap::new(),
+        }
+    }
+
+    // Returns a view of all mip levels of the texture. On a cache miss the
+    // upload is recorded into `builder`, which therefore must not be inside a
+    // render pass.
+    pub fn get_or_upload(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        texture: &Texture,
+    ) -> Result<Arc<ImageView>, MagmaError> {
+        if let Some(view) = self.textures.get(&texture.id()) {
+            return Ok(view.clone());
+        }
+        let view = upload(&self.memory_allocator, builder, texture)?;
+        self.textures.insert(texture.id(), view.clone());
+        Ok(view)
+    }
+
+    // Drops the images of all textures that are no longer in use.
+    pub fn retain(&mut self, texture_ids: &HashSet<u64>) {
+        self.textures.retain(|id, _| texture_ids.contains(id));
+    }
+}
+
+// Copies the pixels into the first mip level and generates the others by
+// blitting every level into the next, half as large one. The format is
+// required to support blits with linear filtering on every device.
+fn upload(
+    memory_allocator: &Arc<StandardMemoryAllocator>,
+    builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    texture: &Texture,
+) -> Result<Arc<ImageView>, MagmaError> {
+    let extent = [texture.width(), texture.height(), 1];
+    let mip_levels = max_mip_levels(extent);
+    let staging_buffer = Buffer::from_iter(
+        memory_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::TRANSFER_SRC,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+            ..Default::default()
+        },
+        texture.pixels().iter().copied(),
+    )?;
+    let image = Image::new(
+        memory_allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: Format::R8G8B8A8_SRGB,
+            extent,
+            mip_levels,
+            usage: ImageUsage::TRANSFER_SRC | ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    )?;
+    builder.copy_buffer_to_image(CopyBufferToImageInfo::buffer_image(
+        staging_buffer,
+        image.clone(),
+    ))?;
+    for level in 1..mip_levels {
+        let subresource = |mip_level| ImageSubresourceLayers {
+            mip_level,
+            ..image.subresource_layers()
+        };
+        let region = ImageBlit {
+            src_subresource: subresource(level - 1),
+            src_offsets: [[0; 3], mip_level_extent(extent, level - 1).unwrap()],
+            dst_subresource: subresource(level),
+            dst_offsets: [[0; 3], mip_level_extent(extent, level).unwrap()],
+            ..Default::default()
+        };
+        builder.blit_image(BlitImageInfo {
+            regions: [region].into(),
+            filter: Filter::Linear,
+            ..BlitImageInfo::images(image.clone(), image.clone())
+        })?;
+    }
+    Ok(ImageView::new_default(image)?)
+}
@@ -0,0 +1,149 @@
+use glam::{Mat3, Mat4, Quat, Vec3};
+
+// Placement of a model, camera or light relative to its parent. Applied as
+// scale, then rotation, then translation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        translation: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        scale: Vec3::ONE,
+    };
+
+    pub fn from_translation(translation: Vec3) -> Self {
+        Transform {
+            translation,
+            ..Transform::IDENTITY
+        }
+    }
+
+    // Decomposes an affine matrix. Shear, e.g. from non-uniform scale
+    // followed by a rotation, can't be represented and is lost.
+    pub fn from_matrix(matrix: Mat4) -> Self {
+        let (scale, rotation, translation) = matrix.to_scale_rotation_translation();
+        Transform {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+
+    // The direction of the local -z axis, where cameras look.
+    pub fn forward(&self) -> Vec3 {
+        self.rotation * Vec3::NEG_Z
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.rotation * Vec3::Y
+    }
+
+    pub fn translate(&mut self, translation: Vec3) {
+        self.translation += translation;
+    }
+
+    // Rotates in the space of the parent, i.e. after the current rotation.
+    pub fn rotate(&mut self, rotation: Quat) {
+        self.rotation = (rotation * self.rotation).normalize();
+    }
+
+    // Rotates around an axis through the own origin, given in parent space.
+    // The angle is in radians.
+    pub fn rotate_around_axis(&mut self, axis: Vec3, angle: f32) {
+        self.rotate(Quat::from_axis_angle(axis.normalize(), angle));
+    }
+
+    // Turns the local -z axis towards `target`, keeping the local y axis as
+    // close to `up` as possible. Does nothing if the target is on the line
+    // through the origin along `up`.
+    pub fn look_at(&mut self, target: Vec3, up: Vec3) {
+        let forward = (target - self.translation).normalize_or_zero();
+        let right = forward.cross(up).normalize_or_zero();
+        if right == Vec3::ZERO {
+            return;
+        }
+        let up = right.cross(forward);
+        self.rotation = Quat::from_mat3(&Mat3::from_cols(right, up, -forward));
+    }
+
+    pub fn looking_at(mut self, target: Vec3, up: Vec3) -> Self {
+        self.look_at(target, up);
+        self
+    }
+
+    // Interpolates linearly between `self` at `t = 0` and `other` at
+    // `t = 1`, with spherical interpolation of the rotation.
+    pub fn lerp(&self, other: &Transform, t: f32) -> Transform {
+        Transform {
+            translation: self.translation.lerp(other.translation, t),
+            rotation: self.rotation.slerp(other.rotation, t),
+            scale: self.scale.lerp(other.scale, t),
+        }
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::IDENTITY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    #[test]
+    fn decompose_matrix() {
+        let transform = Transform {
+            translation: Vec3::new(1.0, 2.0, 3.0),
+            rotation: Quat::from_rotation_y(0.5),
+            scale: Vec3::new(1.0, 2.0, 3.0),
+        };
+        let decomposed = Transform::from_matrix(transform.matrix());
+        assert!(
+            decomposed
+                .translation
+                .abs_diff_eq(transform.translation, 1e-5)
+        );
+        assert!(decomposed.rotation.abs_diff_eq(transform.rotation, 1e-5));
+        assert!(decomposed.scale.abs_diff_eq(transform.scale, 1e-5));
+    }
+
+    #[test]
+    fn look_at_target() {
+        let transform = Transform::from_translation(Vec3::new(0.0, 0.0, 5.0))
+            .looking_at(Vec3::new(5.0, 0.0, 5.0), Vec3::Y);
+        assert!(transform.forward().abs_diff_eq(Vec3::X, 1e-5));
+        assert!(transform.up().abs_diff_eq(Vec3::Y, 1e-5));
+        let view = Mat4::look_at_rh(transform.translation, Vec3::new(5.0, 0.0, 5.0), Vec3::Y);
+        assert!(transform.matrix().inverse().abs_diff_eq(view, 1e-5));
+    }
+
+    #[test]
+    fn interpolate() {
+        let start = Transform::IDENTITY;
+        let mut end = Transform::from_translation(Vec3::new(2.0, 0.0, 0.0));
+        end.rotate_around_axis(Vec3::Y, FRAC_PI_2);
+        let middle = start.lerp(&end, 0.5);
+        assert!(
+            middle
+                .translation
+                .abs_diff_eq(Vec3::new(1.0, 0.0, 0.0), 1e-5)
+        );
+        assert!(
+            middle
+                .rotation
+                .abs_diff_eq(Quat::from_rotation_y(FRAC_PI_2 / 2.0), 1e-5)
+        );
+    }
+}
@@ -0,0 +1,43 @@
+// Magma renders scenes of meshes with Vulkan, into a winit window or, in
+// headless mode, into an offscreen image.
+//
+// let instance = magma::create_instance(InstanceExtensions::empty())?;
+// let mut engine = magma::Engine::new_headless(&instance, [800, 600], &EngineConfig::default())?;
+// engine.set_scene(magma::Scene::load("scenes/cubes.ron")?);
+// engine.capture_frame()?.unwrap().save("frame.png")?;
+
+mod engine;
+
+pub use engine::Engine;
+pub use engine::EngineConfig;
+pub use engine::camera::Camera;
+pub use engine::capture::Frame;
+pub use engine::create_instance;
+pub use engine::debug::{DebugSeverity, create_debug_instance};
+pub use engine::device::{DeviceInfo, DeviceSelection, list_devices};
+pub use engine::error::MagmaError;
+pub use engine::light::{Light, LightKind};
+pub use engine::material::Material;
+pub use engine::mesh::{Mesh, MeshSource, Normal, Position, Uv, compute_normals};
+pub use engine::model::{DEFAULT_COLOR, Model};
+pub use engine::scene::{HierarchyError, ModelHandle, Scene};
+pub use engine::scene_file::SceneFileError;
+pub use engine::target::{FormatPreference, SwapchainConfig};
+pub use engine::texture::{Texture, TextureError};
+pub use engine::transform::Transform;
+pub use engine::{gltf_loader, obj, scene_file};
+
+// The crates whose types appear in the API, so applications use the same
+// versions.
+pub use glam;
+pub use vulkano;
+pub use winit;
+
+// Procedural meshes and the built-in example scenes.
+pub mod primitives {
+    pub use crate::engine::model::{
+        get_capsule_mesh, get_cone_mesh, get_cube_mesh, get_cylinder_mesh, get_icosphere_mesh,
+        get_plane_mesh, get_torus_mesh, get_uv_sphere_mesh,
+    };
+    pub use crate::engine::scene::{get_cube_scene, get_primitives_scene};
+}
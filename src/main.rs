@@ -1,35 +1,37 @@
+use magma::glam::Vec3;
+use magma::vulkano::instance::debug::DebugUtilsMessenger;
+use magma::vulkano::instance::{Instance, InstanceExtensions};
+use magma::vulkano::swapchain::{PresentMode, Surface};
+use magma::winit::application::ApplicationHandler;
+use magma::winit::event::WindowEvent;
+use magma::winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+use magma::winit::keyboard::{Key, NamedKey};
+use magma::winit::window::{Window, WindowId};
+use magma::{
+    DebugSeverity, DeviceSelection, Engine, EngineConfig, FormatPreference, Scene, SwapchainConfig,
+};
 use std::error::Error;
 use std::sync::Arc;
-use vulkano::VulkanLibrary;
-use vulkano::instance::{Instance, InstanceCreateFlags, InstanceCreateInfo};
-use vulkano::swapchain::Surface;
-use winit::application::ApplicationHandler;
-use winit::event::WindowEvent;
-use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
-use winit::window::{Window, WindowId};
+use std::time::Duration;
 
-mod engine;
+const SCREENSHOT_PATH: &str = "screenshot.png";
+// Radians per second, the speed the cubes used to spin with at 60 fps.
+const SPIN_SPEED: f32 = 6.0;
 
 struct App {
     instance: Arc<Instance>,
-    engine: Option<engine::Engine>,
+    // Prints validation messages while alive, only set with `--debug`.
+    _debug_messenger: Option<DebugUtilsMessenger>,
+    engine: Option<Engine>,
 }
 
 impl App {
     fn new(event_loop: &EventLoop<()>) -> Self {
-        let library = VulkanLibrary::new().expect("no local Vulkan library/DLL");
         let required_extensions = Surface::required_extensions(&event_loop).unwrap();
-        let instance = Instance::new(
-            library,
-            InstanceCreateInfo {
-                flags: InstanceCreateFlags::ENUMERATE_PORTABILITY,
-                enabled_extensions: required_extensions,
-                ..Default::default()
-            },
-        )
-        .expect("failed to create Vulkan instance");
+        let (instance, debug_messenger) = create_instance(required_extensions);
         App {
-            instance: instance,
+            instance,
+            _debug_messenger: debug_messenger,
             engine: None,
         }
     }
@@ -39,7 +41,21 @@ impl ApplicationHandler for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         let window_attributes = Window::default_attributes().with_title("Magma v0.1.0");
         let window = Arc::new(event_loop.create_window(window_attributes).unwrap());
-        self.engine = Some(engine::Engine::new(&self.instance, window));
+        let mut engine = match Engine::new(&self.instance, window, &engine_config()) {
+            Ok(engine) => engine,
+            Err(e) => {
+                println!("{e}");
+                event_loop.exit();
+                return;
+            }
+        };
+        engine.set_scene(load_scene());
+        engine.set_update(animate);
+        if let Some(milliseconds) = flag_value("--fixed-timestep") {
+            let milliseconds = milliseconds.parse().expect("invalid timestep");
+            engine.set_fixed_timestep(Some(Duration::from_millis(milliseconds)));
+        }
+        self.engine = Some(engine);
     }
 
     fn window_event(
@@ -55,15 +71,193 @@ impl ApplicationHandler for App {
             WindowEvent::Resized(_) => {
                 self.engine.as_mut().unwrap().recreate_swapchain();
             }
+            WindowEvent::KeyboardInput { event, .. }
+                if event.state.is_pressed() && event.logical_key == Key::Named(NamedKey::F12) =>
+            {
+                match self.engine.as_mut().unwrap().capture_frame() {
+                    Ok(Some(frame)) => match frame.save(SCREENSHOT_PATH) {
+                        Ok(()) => println!("saved screenshot to {SCREENSHOT_PATH}"),
+                        Err(e) => println!("failed to save screenshot: {e}"),
+                    },
+                    Ok(None) => {}
+                    Err(e) => println!("failed to capture screenshot: {e}"),
+                }
+            }
+            WindowEvent::KeyboardInput { event, .. }
+                if event.state.is_pressed() && event.logical_key == Key::Character("v".into()) =>
+            {
+                let engine = self.engine.as_mut().unwrap();
+                let mut config = engine.swapchain_config();
+                config.present_mode = match config.present_mode {
+                    PresentMode::Fifo => PresentMode::Immediate,
+                    _ => PresentMode::Fifo,
+                };
+                println!("present mode {:?}", config.present_mode);
+                engine.set_swapchain_config(config);
+            }
             WindowEvent::RedrawRequested => {
-                self.engine.as_mut().unwrap().draw();
+                if let Err(e) = self.engine.as_mut().unwrap().draw() {
+                    println!("{e}");
+                    event_loop.exit();
+                }
             }
             _ => {}
         }
     }
 }
 
+// Renders a single frame without opening a window, e.g. on CI machines
+// with a software Vulkan implementation, and optionally saves it to a file.
+fn run_headless(output: Option<String>) {
+    let (instance, _debug_messenger) = create_instance(InstanceExtensions::empty());
+    let mut engine = Engine::new_headless(&instance, [800, 600], &engine_config())
+        .expect("failed to create engine");
+    engine.set_scene(load_scene());
+    match output {
+        Some(path) => {
+            let frame = engine.capture_frame().expect("failed to render frame");
+            frame.unwrap().save(&path).expect("failed to save frame");
+        }
+        None => {
+            engine.render_frame().expect("failed to render frame");
+        }
+    }
+}
+
+// Scene passed with `--scene <path|primitives>`, or the demo cubes, without
+// the model named by `--exclude <name>`. With `--export-obj <path>` the scene
+// is also written to an OBJ file and with `--save-scene <path>` to a scene
+// file.
+fn load_scene() -> Scene {
+    let mut scene = match flag_value("--scene") {
+        Some(path) if path == "primitives" => magma::primitives::get_primitives_scene(),
+        Some(path) if path.ends_with(".ron") => Scene::load(&path).expect("failed to load scene"),
+        Some(path) if path.ends_with(".obj") => {
+            magma::obj::load_scene(&path).expect("failed to load scene")
+        }
+        Some(path) => magma::gltf_loader::load(&path).expect("failed to load scene"),
+        None => magma::primitives::get_cube_scene(),
+    };
+    if let Some(name) = flag_value("--exclude") {
+        let handle = scene.find(&name).expect("no model with that name");
+        scene.remove(handle);
+    }
+    if scene.is_empty() {
+        println!("scene has no models");
+    }
+    if let Some(path) = flag_value("--export-obj") {
+        magma::obj::save(&scene, &path).expect("failed to export scene");
+    }
+    if let Some(path) = flag_value("--save-scene") {
+        scene.save(&path).expect("failed to save scene");
+    }
+    scene
+}
+
+// Spins two of the demo cubes while the third one lazily follows the
+// rotation of the second. Does nothing for other scenes.
+fn animate(scene: &mut Scene, delta_time: f32) {
+    let [cube1, cube2, cube3] = ["cube1", "cube2", "cube3"].map(|name| scene.find(name));
+    if let Some(cube1) = cube1.and_then(|cube1| scene.get_mut(cube1)) {
+        cube1
+            .transform
+            .rotate_around_axis(Vec3::X, -SPIN_SPEED * delta_time);
+    }
+    if let Some(cube2) = cube2.and_then(|cube2| scene.get_mut(cube2)) {
+        cube2
+            .transform
+            .rotate_around_axis(Vec3::Z, SPIN_SPEED * delta_time);
+    }
+    if let Some(rotation) = cube2
+        .and_then(|cube2| scene.get(cube2))
+        .map(|cube2| cube2.transform.rotation)
+        && let Some(cube3) = cube3.and_then(|cube3| scene.get_mut(cube3))
+    {
+        let mut target = cube3.transform;
+        target.rotation = rotation;
+        cube3.transform = cube3.transform.lerp(&target, (3.0 * delta_time).min(1.0));
+    }
+}
+
+// With `--debug [error|warning|info|verbose]` the validation layer is enabled
+// and its messages of at least that severity, by default warnings, are printed.
+fn create_instance(
+    enabled_extensions: InstanceExtensions,
+) -> (Arc<Instance>, Option<DebugUtilsMessenger>) {
+    if !std::env::args().any(|arg| arg == "--debug") {
+        let instance =
+            magma::create_instance(enabled_extensions).expect("failed to create instance");
+        return (instance, None);
+    }
+    let min_severity = flag_value("--debug").map_or(DebugSeverity::Warning, |value| {
+        DebugSeverity::parse(&value).expect("invalid debug severity")
+    });
+    magma::create_debug_instance(enabled_extensions, min_severity)
+        .expect("failed to create instance")
+}
+
+// `--present-mode fifo|mailbox|immediate` and `--10-bit` for a 10-bit format
+// if the display supports one.
+fn swapchain_config() -> SwapchainConfig {
+    let present_mode = match flag_value("--present-mode").as_deref() {
+        None | Some("fifo") => PresentMode::Fifo,
+        Some("mailbox") => PresentMode::Mailbox,
+        Some("immediate") => PresentMode::Immediate,
+        Some(mode) => panic!("invalid present mode {mode}"),
+    };
+    let format = if std::env::args().any(|arg| arg == "--10-bit") {
+        FormatPreference::TenBit
+    } else {
+        FormatPreference::Srgb
+    };
+    SwapchainConfig {
+        format,
+        present_mode,
+    }
+}
+
+// Device passed with `--device <selection>`, otherwise from `MAGMA_DEVICE`,
+// the swapchain settings and `--frames-in-flight <count>`.
+fn engine_config() -> EngineConfig {
+    let device = flag_value("--device").map_or_else(DeviceSelection::from_env, |value| {
+        DeviceSelection::parse(&value)
+    });
+    let frames_in_flight = flag_value("--frames-in-flight")
+        .map_or(EngineConfig::default().frames_in_flight, |count| {
+            count.parse().expect("invalid number of frames in flight")
+        });
+    EngineConfig {
+        device,
+        swapchain: swapchain_config(),
+        frames_in_flight,
+    }
+}
+
+// Prints the devices usable for headless rendering. Presentation support is
+// only known once a window exists.
+fn list_devices() {
+    let instance =
+        magma::create_instance(InstanceExtensions::empty()).expect("failed to create instance");
+    for device in magma::list_devices(&instance, None).expect("failed to list devices") {
+        println!("{device}");
+    }
+}
+
+// Value following a command line flag, unless it is another flag.
+fn flag_value(flag: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != flag).skip(1);
+    args.next().filter(|value| !value.starts_with("--"))
+}
+
 fn main() -> Result<(), impl Error> {
+    if std::env::args().any(|arg| arg == "--list-devices") {
+        list_devices();
+        return Ok(());
+    }
+    if std::env::args().any(|arg| arg == "--headless") {
+        run_headless(flag_value("--headless"));
+        return Ok(());
+    }
     let event_loop = EventLoop::new().unwrap();
     // ControlFlow::Poll continuously runs the event loop, even if the OS hasn't
     // dispatched any events. This is ideal for games and similar applications.
@@ -1,14 +1,140 @@
 #version 450
 
 layout(location = 0) in vec3 v_normal;
+layout(location = 1) in vec3 v_position;
+layout(location = 2) in vec2 v_uv;
 layout(location = 0) out vec4 f_color;
 
-const vec3 LIGHT = vec3(0.0, 0.0, 1.0);
+layout(push_constant) uniform Material {
+    vec4 base_color;
+    vec3 emissive;
+    float shininess;
+} material;
 
-void main() {
-    float brightness = dot(normalize(v_normal), normalize(LIGHT));
-    vec3 dark_color = vec3(0.6, 0.0, 0.0);
-    vec3 regular_color = vec3(1.0, 0.0, 0.0);
+// Set for render targets whose format does not encode sRGB on write.
+layout(constant_id = 0) const bool ENCODE_SRGB = false;
+
+// Multiplied with the base color. Untextured models use a white texture.
+layout(set = 1, binding = 0) uniform sampler2D base_texture;
+
+const uint MAX_LIGHTS = 16;
+const float DIRECTIONAL = 0.0;
+const float POINT = 1.0;
+
+// Positions and directions are in view space, see `shader_light`.
+struct Light {
+    // w is the kind, 0 for directional, 1 for point and 2 for spot lights.
+    vec4 position;
+    // Direction the light shines in, w is the cosine of the inner cone angle.
+    vec4 direction;
+    // Color times intensity, w is the cosine of the outer cone angle.
+    vec4 color;
+    // Constant, linear and quadratic coefficients of the falloff, w is the
+    // layer of the shadow map or negative without shadows.
+    vec4 attenuation;
+    // From view space to the clip space of the shadow map.
+    mat4 shadow_matrix;
+};
+
+layout(set = 0, binding = 1) uniform Lights {
+    Light lights[MAX_LIGHTS];
+    vec3 ambient;
+    uint light_count;
+} scene;
+
+// One layer per shadow casting light, sampled with depth comparison.
+layout(set = 0, binding = 2) uniform sampler2DArrayShadow shadow_maps;
+
+// Direction towards the headlight of scenes without lights, i.e. it shines
+// from the camera.
+const vec3 HEADLIGHT = vec3(0.0, 0.0, 1.0);
+
+// Blinn-Phong highlight of a light from `light_dir`.
+float specular(vec3 normal, vec3 light_dir) {
+    if (material.shininess <= 0.0) {
+        return 0.0;
+    }
+    vec3 halfway = normalize(light_dir - normalize(v_position));
+    return pow(max(dot(normal, halfway), 0.0), material.shininess);
+}
 
-    f_color = vec4(mix(dark_color, regular_color, brightness), 1.0);
+vec3 headlight(vec3 normal, vec3 base_color) {
+    float brightness = dot(normal, HEADLIGHT);
+    vec3 dark_color = 0.6 * base_color;
+    vec3 regular_color = base_color;
+    vec3 color = mix(dark_color, regular_color, brightness);
+    return color + vec3(specular(normal, HEADLIGHT));
+}
+
+// Fraction of the light reaching the fragment, averaged over 3x3 texels of
+// the shadow map to soften the edges.
+float shadow(Light light) {
+    if (light.attenuation.w < 0.0) {
+        return 1.0;
+    }
+    vec4 clip = light.shadow_matrix * vec4(v_position, 1.0);
+    vec3 ndc = clip.xyz / clip.w;
+    vec2 uv = ndc.xy * 0.5 + 0.5;
+    // Outside of the map nothing blocks the light.
+    bool outside = any(lessThan(uv, vec2(0.0))) || any(greaterThan(uv, vec2(1.0)));
+    if (clip.w <= 0.0 || ndc.z > 1.0 || outside) {
+        return 1.0;
+    }
+    vec2 texel = 1.0 / vec2(textureSize(shadow_maps, 0).xy);
+    float lit = 0.0;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            vec2 offset = vec2(x, y) * texel;
+            lit += texture(shadow_maps, vec4(uv + offset, light.attenuation.w, ndc.z));
+        }
+    }
+    return lit / 9.0;
+}
+
+// Blinn-Phong diffuse and specular reflection of one light.
+vec3 shade(Light light, vec3 normal, vec3 base_color) {
+    vec3 light_dir = -light.direction.xyz;
+    float falloff = 1.0;
+    if (light.position.w != DIRECTIONAL) {
+        vec3 to_light = light.position.xyz - v_position;
+        float dist = length(to_light);
+        light_dir = to_light / dist;
+        falloff = 1.0 / dot(light.attenuation.xyz, vec3(1.0, dist, dist * dist));
+        if (light.position.w != POINT) {
+            float cos_angle = dot(-light_dir, light.direction.xyz);
+            falloff *= smoothstep(light.color.w, light.direction.w, cos_angle);
+        }
+    }
+    float diffuse = max(dot(normal, light_dir), 0.0);
+    vec3 reflected = diffuse * base_color;
+    if (diffuse > 0.0) {
+        reflected += vec3(specular(normal, light_dir));
+    }
+    return shadow(light) * falloff * light.color.rgb * reflected;
+}
+
+vec3 encode_srgb(vec3 linear) {
+    linear = clamp(linear, 0.0, 1.0);
+    vec3 low = 12.92 * linear;
+    vec3 high = 1.055 * pow(linear, vec3(1.0 / 2.4)) - 0.055;
+    return mix(low, high, greaterThan(linear, vec3(0.0031308)));
+}
+
+void main() {
+    vec4 base_color = material.base_color * texture(base_texture, v_uv);
+    vec3 normal = normalize(v_normal);
+    vec3 color;
+    if (scene.light_count == 0) {
+        color = headlight(normal, base_color.rgb);
+    } else {
+        color = scene.ambient * base_color.rgb;
+        for (uint i = 0; i < min(scene.light_count, MAX_LIGHTS); i++) {
+            color += shade(scene.lights[i], normal, base_color.rgb);
+        }
+    }
+    color += material.emissive;
+    if (ENCODE_SRGB) {
+        color = encode_srgb(color);
+    }
+    f_color = vec4(color, base_color.a);
 }
@@ -0,0 +1,14 @@
+#version 450
+
+layout(location = 0) in vec3 position;
+// Per instance attributes, the world matrix occupies locations 1 to 4.
+layout(location = 1) in mat4 world;
+
+layout(push_constant) uniform Shadow {
+    // From world space to the clip space of the shadow map.
+    mat4 light_matrix;
+} shadow;
+
+void main() {
+    gl_Position = shadow.light_matrix * world * vec4(position, 1.0);
+}
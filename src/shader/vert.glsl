@@ -2,17 +2,24 @@
 
 layout(location = 0) in vec3 position;
 layout(location = 1) in vec3 normal;
+layout(location = 2) in vec2 uv;
+// Per instance attributes, the world matrix occupies locations 3 to 6.
+layout(location = 3) in mat4 world;
 
 layout(location = 0) out vec3 v_normal;
+layout(location = 1) out vec3 v_position;
+layout(location = 2) out vec2 v_uv;
 
 layout(set = 0, binding = 0) uniform Data {
-    mat4 world;
     mat4 view;
     mat4 proj;
 } uniforms;
 
 void main() {
-    mat4 worldview = uniforms.view * uniforms.world;
+    mat4 worldview = uniforms.view * world;
+    vec4 view_position = worldview * vec4(position, 1.0);
     v_normal = transpose(inverse(mat3(worldview))) * normal;
-    gl_Position = uniforms.proj * worldview * vec4(position, 1.0);
+    v_position = view_position.xyz;
+    v_uv = uv;
+    gl_Position = uniforms.proj * view_position;
 }